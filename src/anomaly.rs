@@ -0,0 +1,374 @@
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::log_entry::LogEntry;
+
+const HOUR_SECS: i64 = 3600;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Baseline {
+    /// The --baseline-hours right before the current window
+    #[default]
+    Previous,
+    /// The same hours one day earlier
+    Yesterday,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum Dimension {
+    Ip,
+    Url,
+    Ua,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub growth: f64,
+    pub z_score: f64,
+    pub min_requests: usize,
+}
+
+/// A value whose share of the traffic grew between the baseline and the current window.
+#[derive(Serialize, Debug, Clone)]
+pub struct Anomaly {
+    pub dimension: Dimension,
+    pub value: String,
+    pub current: usize,
+    /// Requests expected in the current window from the baseline hourly mean
+    pub expected: f64,
+    pub current_share: f64,
+    pub baseline_share: f64,
+    /// Growth of the share, `None` when the value is absent from the baseline
+    pub growth: Option<f64>,
+    pub z_score: f64,
+}
+
+/// Hourly request counts per IP, URL and UA, compared between two windows.
+#[derive(Debug)]
+pub struct WindowComparison {
+    window_hours: i64,
+    baseline: Baseline,
+    baseline_hours: i64,
+    totals: BTreeMap<i64, usize>,
+    ips: HashMap<String, BTreeMap<i64, usize>>,
+    urls: HashMap<String, BTreeMap<i64, usize>>,
+    uas: HashMap<String, BTreeMap<i64, usize>>,
+}
+
+impl WindowComparison {
+    pub fn new(window_hours: u32, baseline: Baseline, baseline_hours: u32) -> Self {
+        let window_hours = i64::from(window_hours.max(1));
+        Self {
+            window_hours,
+            baseline,
+            baseline_hours: match baseline {
+                Baseline::Previous => i64::from(baseline_hours.max(1)),
+                Baseline::Yesterday => window_hours,
+            },
+            totals: BTreeMap::new(),
+            ips: HashMap::new(),
+            urls: HashMap::new(),
+            uas: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, ip: &str, entry: &LogEntry) {
+        let Some(timestamp) = entry.timestamp else {
+            return;
+        };
+        let hour = timestamp.timestamp().div_euclid(HOUR_SECS);
+        *self.totals.entry(hour).or_insert(0) += 1;
+
+        let count = |map: &mut HashMap<String, BTreeMap<i64, usize>>, value: &str| {
+            let hours = match map.get_mut(value) {
+                Some(hours) => hours,
+                None => map.entry(value.to_string()).or_default(),
+            };
+            *hours.entry(hour).or_insert(0) += 1;
+        };
+        count(&mut self.ips, ip);
+        if let Some(url) = &entry.url {
+            count(&mut self.urls, url);
+        }
+        if let Some(ua) = &entry.ua {
+            count(&mut self.uas, ua);
+        }
+    }
+
+    /// First hours of the current and the baseline window, ending at the latest hour seen.
+    fn windows(&self) -> Option<(i64, i64)> {
+        let latest = *self.totals.keys().last()?;
+        let current = latest - self.window_hours + 1;
+        let baseline = match self.baseline {
+            Baseline::Previous => current - self.baseline_hours,
+            Baseline::Yesterday => current - 24,
+        };
+        Some((current, baseline))
+    }
+
+    pub fn current_window(&self) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        self.window_range(|(current, _)| current, self.window_hours)
+    }
+
+    pub fn baseline_window(&self) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        self.window_range(|(_, baseline)| baseline, self.baseline_hours)
+    }
+
+    fn window_range(
+        &self,
+        start: impl Fn((i64, i64)) -> i64,
+        hours: i64,
+    ) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        let start = start(self.windows()?) * HOUR_SECS;
+        let end = start + hours * HOUR_SECS;
+        Some((
+            DateTime::from_timestamp(start, 0)?.with_timezone(&Local),
+            DateTime::from_timestamp(end, 0)?.with_timezone(&Local),
+        ))
+    }
+
+    fn sum(hours: &BTreeMap<i64, usize>, start: i64, len: i64) -> usize {
+        hours
+            .range(start..start + len)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Values that exceed the growth or z-score threshold, sorted by z-score.
+    /// Empty when no requests fall into the baseline window.
+    pub fn anomalies(&self, thresholds: &Thresholds) -> Vec<Anomaly> {
+        let Some((current_start, baseline_start)) = self.windows() else {
+            return Vec::new();
+        };
+        let current_total = Self::sum(&self.totals, current_start, self.window_hours);
+        let baseline_total = Self::sum(&self.totals, baseline_start, self.baseline_hours);
+        if current_total == 0 || baseline_total == 0 {
+            return Vec::new();
+        }
+
+        let mut anomalies = Vec::new();
+        for (dimension, map) in [
+            (Dimension::Ip, &self.ips),
+            (Dimension::Url, &self.urls),
+            (Dimension::Ua, &self.uas),
+        ] {
+            for (value, hours) in map {
+                let current = Self::sum(hours, current_start, self.window_hours);
+                if current < thresholds.min_requests {
+                    continue;
+                }
+                let samples: Vec<f64> = (baseline_start..baseline_start + self.baseline_hours)
+                    .map(|hour| hours.get(&hour).copied().unwrap_or(0) as f64)
+                    .collect();
+                let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+                let variance = samples
+                    .iter()
+                    .map(|count| (count - mean).powi(2))
+                    .sum::<f64>()
+                    / samples.len() as f64;
+
+                // scale the hourly baseline to the window, at least Poisson noise
+                let window = self.window_hours as f64;
+                let expected = mean * window;
+                let deviation = (variance * window).sqrt().max(expected.max(1.0).sqrt());
+                let z_score = (current as f64 - expected) / deviation;
+
+                let current_share = current as f64 / current_total as f64;
+                let baseline_share = mean * samples.len() as f64 / baseline_total as f64;
+                let growth = (baseline_share > 0.0).then(|| current_share / baseline_share);
+
+                if growth.is_none_or(|growth| growth >= thresholds.growth)
+                    || z_score >= thresholds.z_score
+                {
+                    anomalies.push(Anomaly {
+                        dimension,
+                        value: value.clone(),
+                        current,
+                        expected,
+                        current_share,
+                        baseline_share,
+                        growth,
+                        z_score,
+                    });
+                }
+            }
+        }
+        anomalies.sort_unstable_by(|a, b| {
+            b.z_score
+                .total_cmp(&a.z_score)
+                .then_with(|| a.value.cmp(&b.value))
+        });
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOOSE: Thresholds = Thresholds {
+        growth: f64::INFINITY,
+        z_score: f64::INFINITY,
+        min_requests: 0,
+    };
+
+    /// Requests per (hour, IP), every request with the same URL and UA.
+    fn comparison(
+        window_hours: u32,
+        baseline: Baseline,
+        baseline_hours: u32,
+        traffic: &[(i64, &str, usize)],
+    ) -> WindowComparison {
+        let mut comparison = WindowComparison::new(window_hours, baseline, baseline_hours);
+        for &(hour, ip, count) in traffic {
+            let entry = LogEntry {
+                timestamp: DateTime::from_timestamp(hour * HOUR_SECS, 0)
+                    .map(|timestamp| timestamp.with_timezone(&Local)),
+                url: Some("GET / HTTP/1.1".to_string()),
+                referrer: None,
+                ua: Some("ua".to_string()),
+                status: Some(200),
+                bytes: None,
+                duration_ms: None,
+            };
+            for _ in 0..count {
+                comparison.add(ip, &entry);
+            }
+        }
+        comparison
+    }
+
+    fn hours(range: Option<(DateTime<Local>, DateTime<Local>)>) -> (i64, i64) {
+        let (start, end) = range.unwrap();
+        (start.timestamp() / HOUR_SECS, end.timestamp() / HOUR_SECS)
+    }
+
+    fn flagged_ips(comparison: &WindowComparison, thresholds: Thresholds) -> Vec<String> {
+        comparison
+            .anomalies(&thresholds)
+            .into_iter()
+            .filter(|anomaly| anomaly.dimension == Dimension::Ip)
+            .map(|anomaly| anomaly.value)
+            .collect()
+    }
+
+    /// A and B at 90 and 10 requests per hour for three hours, then B jumps to 60.
+    fn burst() -> WindowComparison {
+        let mut traffic = Vec::new();
+        for hour in 100..103 {
+            traffic.push((hour, "A", 90));
+            traffic.push((hour, "B", 10));
+        }
+        traffic.push((103, "A", 90));
+        traffic.push((103, "B", 60));
+        comparison(1, Baseline::Previous, 3, &traffic)
+    }
+
+    #[test]
+    fn previous_baseline_ends_where_the_window_starts() {
+        let comparison = comparison(2, Baseline::Previous, 3, &[(5, "A", 1), (10, "A", 1)]);
+        assert_eq!(hours(comparison.current_window()), (9, 11));
+        assert_eq!(hours(comparison.baseline_window()), (6, 9));
+    }
+
+    #[test]
+    fn yesterday_baseline_is_the_same_hours_a_day_earlier() {
+        let comparison = comparison(2, Baseline::Yesterday, 12, &[(5, "A", 1), (30, "A", 1)]);
+        assert_eq!(hours(comparison.current_window()), (29, 31));
+        assert_eq!(hours(comparison.baseline_window()), (5, 7));
+    }
+
+    #[test]
+    fn growth_threshold_flags_a_growing_share() {
+        let comparison = burst();
+        let thresholds = Thresholds {
+            growth: 3.0,
+            ..LOOSE
+        };
+        assert_eq!(flagged_ips(&comparison, thresholds), ["B"]);
+        let thresholds = Thresholds {
+            growth: 5.0,
+            ..LOOSE
+        };
+        assert!(flagged_ips(&comparison, thresholds).is_empty());
+
+        let anomaly = comparison.anomalies(&Thresholds {
+            growth: 3.0,
+            ..LOOSE
+        });
+        let b = anomaly.iter().find(|anomaly| anomaly.value == "B").unwrap();
+        assert_eq!(b.current, 60);
+        assert!((b.growth.unwrap() - 4.0).abs() < 1e-9);
+        assert!((b.expected - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn z_score_threshold_flags_counts_above_the_baseline() {
+        let comparison = burst();
+        // B: (60 - 10) / sqrt(10) is about 15.8, A stays at its mean
+        let thresholds = Thresholds {
+            z_score: 15.0,
+            ..LOOSE
+        };
+        assert_eq!(flagged_ips(&comparison, thresholds), ["B"]);
+        let thresholds = Thresholds {
+            z_score: 16.0,
+            ..LOOSE
+        };
+        assert!(flagged_ips(&comparison, thresholds).is_empty());
+    }
+
+    #[test]
+    fn values_missing_from_the_baseline_are_new() {
+        let comparison = comparison(
+            1,
+            Baseline::Previous,
+            2,
+            &[(1, "A", 10), (2, "A", 10), (2, "C", 1)],
+        );
+        let anomalies = comparison.anomalies(&LOOSE);
+        let c = anomalies
+            .iter()
+            .find(|anomaly| anomaly.value == "C")
+            .unwrap();
+        assert_eq!(c.growth, None);
+        assert_eq!(flagged_ips(&comparison, LOOSE), ["C"]);
+    }
+
+    #[test]
+    fn min_requests_skips_small_values() {
+        let comparison = burst();
+        let thresholds = Thresholds {
+            growth: 3.0,
+            min_requests: 61,
+            ..LOOSE
+        };
+        assert!(flagged_ips(&comparison, thresholds).is_empty());
+        let thresholds = Thresholds {
+            growth: 3.0,
+            min_requests: 60,
+            ..LOOSE
+        };
+        assert_eq!(flagged_ips(&comparison, thresholds), ["B"]);
+    }
+
+    #[test]
+    fn empty_baseline_window_reports_nothing() {
+        let comparison = comparison(1, Baseline::Previous, 3, &[(10, "A", 5), (10, "B", 50)]);
+        assert!(
+            comparison
+                .anomalies(&Thresholds {
+                    growth: 0.0,
+                    z_score: 0.0,
+                    min_requests: 0
+                })
+                .is_empty()
+        );
+        assert!(comparison.baseline_window().is_some());
+        let comparison = WindowComparison::new(1, Baseline::Previous, 3);
+        assert!(comparison.anomalies(&LOOSE).is_empty());
+        assert!(comparison.current_window().is_none());
+    }
+}
@@ -1,8 +1,19 @@
 use clap::Parser;
 use clap::{self, ArgAction};
-use std::path::PathBuf;
+use std::{net::SocketAddr, path::PathBuf};
 
-#[derive(Parser, Debug)]
+use crate::{
+    anomaly::Baseline,
+    crawler_verify::parse_dns_server,
+    grouping::GroupBy,
+    query_params::MAX_TRACKED_VALUES,
+    ranking::{SortKey, SortOrder},
+    reputation::parse_list,
+    scoring::{Signal, parse_weight},
+    ua_classifier::UaCategory,
+};
+
+#[derive(Parser, Debug, Clone)]
 #[command(name = "accessparser")]
 #[command(about = "A program to parse access logs and retrieve top IP addresses")]
 pub struct CliOptions {
@@ -35,4 +46,230 @@ pub struct CliOptions {
 
     #[arg(short = 'f', long = "no-footer", default_value_t = true, action= ArgAction::SetFalse)]
     pub footer: bool,
+
+    /// Bucket size of the per IP request timeline, in minutes
+    #[arg(short = 'i', long, default_value_t = 60)]
+    pub timeline_interval: u32,
+
+    /// Show requests over time of all clients, bucketed by --timeline-interval
+    #[arg(short = 'g', long, default_value_t = false)]
+    pub global_timeline: bool,
+
+    /// Ranking of IPs, further comma separated keys break ties
+    #[arg(long, alias = "sort", value_enum, value_delimiter = ',', default_value = "count")]
+    pub sort_by: Vec<SortKey>,
+
+    #[arg(long, value_enum, default_value_t = SortOrder::Desc)]
+    pub order: SortOrder,
+
+    /// Show the N most common URLs, UAs and referring domains across all IPs
+    #[arg(short = 'T', long, default_value_t = 0)]
+    pub global_top: usize,
+
+    /// Reconstruct sessions per IP and UA, split after this many idle minutes. Also ends the
+    /// sessions of --ua-rotation, 30 minutes when not set
+    #[arg(long, value_name = "MINUTES")]
+    pub session_gap: Option<u32>,
+
+    /// Count IPs in bounded memory sketches, for logs with millions of distinct IPs. Memory
+    /// still grows with the requests of the --sketch-capacity IPs recounted in the full pass,
+    /// options tracking every IP, URL or UA are rejected
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = [
+            "global_timeline",
+            "global_top",
+            "query_params",
+            "compare_window",
+            "detect_logins",
+            "missing_paths",
+            "clusters",
+        ]
+    )]
+    pub approximate: bool,
+
+    /// Number of IPs the --approximate top-k sketch keeps
+    #[arg(long, default_value_t = 10_000)]
+    pub sketch_capacity: usize,
+
+    /// Drop the query string from URLs
+    #[arg(long, default_value_t = false)]
+    pub strip_query: bool,
+
+    /// Replace numeric IDs, UUIDs and hashes in URL paths with placeholders
+    #[arg(long, default_value_t = false)]
+    pub collapse_ids: bool,
+
+    /// Rewrite URL paths with a regex rule, PATTERN=>REPLACEMENT (repeatable)
+    #[arg(long, value_name = "RULE")]
+    pub rewrite: Vec<String>,
+
+    /// Report query string parameters per IP and overall
+    #[arg(long, default_value_t = false)]
+    pub query_params: bool,
+
+    /// Flag an IP as fuzzing a parameter once it sent this many distinct values, at most 1000
+    #[arg(
+        long,
+        default_value_t = 100,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=MAX_TRACKED_VALUES as u64)
+    )]
+    pub fuzz_threshold: usize,
+
+    /// Compare the last N hours against a baseline and report IPs, URLs and UAs that grew
+    #[arg(long, value_name = "HOURS")]
+    pub compare_window: Option<u32>,
+
+    /// Hours the --compare-window is compared against
+    #[arg(long, value_enum, default_value_t = Baseline::Previous)]
+    pub baseline: Baseline,
+
+    /// Length of the previous baseline, in hours
+    #[arg(long, default_value_t = 24)]
+    pub baseline_hours: u32,
+
+    /// Report values whose share of requests grew by this factor
+    #[arg(long, default_value_t = 3.0)]
+    pub growth_threshold: f64,
+
+    /// Report values whose request count is this many standard deviations above the baseline
+    #[arg(long, default_value_t = 3.0)]
+    pub z_threshold: f64,
+
+    /// Ignore values with fewer requests in the current window
+    #[arg(long, default_value_t = 10)]
+    pub anomaly_min_requests: usize,
+
+    /// Only show IPs requesting at near-constant intervals
+    #[arg(long, default_value_t = false)]
+    pub regular_only: bool,
+
+    /// Highest coefficient of variation of request gaps counted as regular, by --regular-only
+    /// and the timing score signal
+    #[arg(long, default_value_t = 0.3)]
+    pub max_cv: f64,
+
+    /// Show how often each IP changes its user agent, per hour and per session
+    #[arg(long, default_value_t = false)]
+    pub ua_rotation: bool,
+
+    /// Only show IPs sending at least N distinct user agents within one hour
+    #[arg(long, value_name = "N")]
+    pub min_uas_per_hour: Option<usize>,
+
+    /// Only show IPs whose dominant user agent is in one of these categories
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub ua_category: Vec<UaCategory>,
+
+    /// Hide IPs whose dominant user agent is in one of these categories
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub exclude_ua_category: Vec<UaCategory>,
+
+    /// Verify IPs claiming to be Google, Bing, Yandex or Apple crawlers with forward-confirmed reverse DNS
+    #[arg(long, default_value_t = false)]
+    pub verify_crawlers: bool,
+
+    /// Hide crawlers verified by --verify-crawlers, unless they fell into a trap
+    #[arg(long, default_value_t = false, requires = "verify_crawlers")]
+    pub hide_verified_crawlers: bool,
+
+    /// DNS server used for crawler verification, IP or IP:PORT (default: system resolver)
+    #[arg(long, value_name = "ADDR", value_parser = parse_dns_server)]
+    pub dns_server: Option<SocketAddr>,
+
+    /// Match requests against the built-in attack signatures
+    #[arg(long, default_value_t = false)]
+    pub detect_attacks: bool,
+
+    /// Additional attack signatures from a TOML file of [[rule]] tables, implies --detect-attacks (repeatable)
+    #[arg(long, value_name = "FILE")]
+    pub rules: Vec<PathBuf>,
+
+    /// Show a suspicion score per IP, also enabled by --sort-by score and --slack-min-score
+    #[arg(long, default_value_t = false)]
+    pub score: bool,
+
+    /// Weight of a score signal, SIGNAL=WEIGHT (repeatable or comma separated)
+    #[arg(long, value_name = "SIGNAL=WEIGHT", value_delimiter = ',', value_parser = parse_weight)]
+    pub score_weight: Vec<(Signal, f64)>,
+
+    /// Only send IPs with at least this suspicion score to Slack
+    #[arg(long, value_name = "SCORE")]
+    pub slack_min_score: Option<f64>,
+
+    /// Report failed and successful logins per IP and subnet, including distributed attacks
+    #[arg(long, default_value_t = false)]
+    pub detect_logins: bool,
+
+    /// Login endpoints, a POST to one of them is a login attempt
+    #[arg(long, value_delimiter = ',', default_value = "/login,/wp-login.php")]
+    pub login_paths: Vec<String>,
+
+    /// Failed logins of an IP or subnet before it is reported
+    #[arg(long, default_value_t = 10)]
+    pub login_min_failures: usize,
+
+    /// Window in minutes in which many low volume IPs failing on one login path count as credential stuffing
+    #[arg(long, value_name = "MINUTES", default_value_t = 60)]
+    pub login_window: u32,
+
+    /// Flag IPs probing many missing paths, see --scan-min-ratio and --scan-min-paths
+    #[arg(long, default_value_t = false)]
+    pub detect_scans: bool,
+
+    /// Share of 404 responses an IP needs to be flagged as scanning
+    #[arg(long, default_value_t = 0.5)]
+    pub scan_min_ratio: f64,
+
+    /// Distinct missing paths an IP needs to be flagged as scanning
+    #[arg(long, default_value_t = 20)]
+    pub scan_min_paths: usize,
+
+    /// Show the N most requested missing paths across all IPs, with their referrers
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub missing_paths: usize,
+
+    /// Cluster IPs sharing user agent, first URLs, referrer and request rhythm, or a rare user agent, to find botnets
+    #[arg(long, default_value_t = false)]
+    pub clusters: bool,
+
+    /// IPs a cluster needs before it is reported
+    #[arg(long, default_value_t = 5)]
+    pub cluster_min_size: usize,
+
+    /// robots.txt to check self-declared crawlers against, for disallowed URLs and Crawl-delay.
+    /// URLs are checked as logged, before --collapse-ids, --strip-query and --rewrite
+    #[arg(long, value_name = "FILE")]
+    pub robots: Option<PathBuf>,
+
+    /// IP reputation list of IPs and CIDRs, [NAME=]PATH, the name defaults to the file name (repeatable)
+    #[arg(long, value_name = "LIST", value_parser = parse_list)]
+    pub reputation: Vec<(String, PathBuf)>,
+
+    /// Only show IPs on one of these reputation lists
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', requires = "reputation")]
+    pub listed_in: Vec<String>,
+
+    /// Hidden URLs no human requests, `*` matches any characters. IPs requesting one are always shown, regardless of --max-ips and the --filter-* and category filters
+    #[arg(long, value_name = "PATHS", value_delimiter = ',')]
+    pub trap_paths: Vec<String>,
+
+    /// Aggregate requests by IP, network or autonomous system
+    #[arg(long, value_enum, default_value_t = GroupBy::Ip)]
+    pub group_by: GroupBy,
+
+    /// IP to ASN database (iptoasn.com TSV), required by --group-by asn
+    #[arg(long, value_name = "FILE")]
+    pub asn_db: Option<PathBuf>,
+
+    /// Compare FILE_PATH against an older log or saved JSON report (.json). Logs are compared
+    /// in full, --max-ips and --global-top only limit the printed rows. Against a saved report
+    /// only the rows it holds are compared
+    #[arg(long, value_name = "OLD_PATH")]
+    pub diff_against: Option<PathBuf>,
+
+    /// Print the report as JSON instead of text
+    #[arg(short = 'j', long, default_value_t = false)]
+    pub json: bool,
 }
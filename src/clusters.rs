@@ -0,0 +1,328 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    grouping::{GroupBy, Grouping},
+    log_entry::LogEntry,
+};
+
+/// Requests of an IP that make up its URL sequence.
+const SEQUENCE_LENGTH: usize = 3;
+/// A user agent sent by less than this share of all IPs is rare.
+const RARE_UA_SHARE: f64 = 0.01;
+
+#[derive(Debug, Default)]
+struct Traits {
+    count: usize,
+    ua: Option<String>,
+    referrer: Option<String>,
+    urls: Vec<String>,
+    first: Option<DateTime<Local>>,
+    last: Option<DateTime<Local>>,
+}
+
+impl Traits {
+    /// Log2 bucket of the average gap between requests, similar rhythms share a bucket.
+    fn timing_bucket(&self) -> Option<u32> {
+        let (first, last) = (self.first?, self.last?);
+        if self.count < 2 {
+            return None;
+        }
+        let gap = (last - first).num_seconds().unsigned_abs() / (self.count as u64 - 1);
+        Some((gap + 1).ilog2())
+    }
+
+    /// First URLs the IP is compared on, only the first one without a rhythm.
+    fn sequence(&self) -> &[String] {
+        match self.timing_bucket() {
+            Some(_) => &self.urls,
+            None => &self.urls[..self.urls.len().min(1)],
+        }
+    }
+}
+
+/// What the IPs of one cluster share.
+#[derive(Debug, Hash, PartialEq, Eq)]
+enum Fingerprint<'a> {
+    /// IPs with a rare user agent, whatever else they do
+    RareUa(&'a str),
+    /// Without a rhythm only the first URL counts
+    Behavior {
+        ua: &'a str,
+        referrer: &'a str,
+        urls: &'a [String],
+        timing: Option<u32>,
+    },
+}
+
+/// Traits of every IP, for grouping clients that behave identically.
+#[derive(Debug, Default)]
+pub struct ClusterTracker {
+    ips: HashMap<String, Traits>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Cluster {
+    pub ua: String,
+    /// None when the members send different referrers
+    pub referrer: Option<String>,
+    /// First URLs all members requested
+    pub urls: Vec<String>,
+    /// Average gap between requests of a member, as a power of two range in seconds,
+    /// None when the members share no rhythm
+    pub gap_secs: Option<(u64, u64)>,
+    pub rare_ua: bool,
+    pub requests: usize,
+    pub ips: Vec<String>,
+    pub subnets: Vec<(String, usize)>,
+    /// Member countries, only known with --asn-db
+    pub countries: Vec<(String, usize)>,
+}
+
+/// The value every item has, None when they differ.
+fn shared<T: PartialEq>(mut values: impl Iterator<Item = T>) -> Option<T> {
+    let first = values.next()?;
+    values.all(|value| value == first).then_some(first)
+}
+
+impl ClusterTracker {
+    pub fn add(&mut self, ip: &str, entry: &LogEntry) {
+        let traits = match self.ips.get_mut(ip) {
+            Some(traits) => traits,
+            None => self.ips.entry(ip.to_string()).or_default(),
+        };
+        traits.count += 1;
+        if traits.ua.is_none() {
+            traits.ua = entry.ua.clone();
+        }
+        if traits.referrer.is_none() {
+            traits.referrer = entry.referrer.clone().filter(|referrer| referrer != "-");
+        }
+        if traits.urls.len() < SEQUENCE_LENGTH
+            && let Some(url) = &entry.url
+        {
+            traits.urls.push(url.clone());
+        }
+        if let Some(timestamp) = entry.timestamp {
+            traits.first = Some(traits.first.map_or(timestamp, |first| first.min(timestamp)));
+            traits.last = Some(traits.last.map_or(timestamp, |last| last.max(timestamp)));
+        }
+    }
+
+    /// Groups of at least `min_size` IPs sharing user agent, first URLs, referrer and
+    /// request rhythm. IPs without a rhythm are grouped on user agent, first URL and
+    /// referrer, IPs with a rare user agent on the user agent alone.
+    /// Clusters with a rare user agent come first, then the largest.
+    pub fn clusters(&self, min_size: usize, grouping: &Grouping) -> Vec<Cluster> {
+        let subnets = Grouping::new(GroupBy::Slash24, None);
+        let mut ua_ips: HashMap<&str, usize> = HashMap::new();
+        for traits in self.ips.values() {
+            *ua_ips
+                .entry(traits.ua.as_deref().unwrap_or("-"))
+                .or_insert(0) += 1;
+        }
+        let is_rare = |ua: &str| (ua_ips[ua] as f64 / self.ips.len() as f64) < RARE_UA_SHARE;
+
+        let mut groups: HashMap<Fingerprint, Vec<(&String, &Traits)>> = HashMap::new();
+        for (ip, traits) in &self.ips {
+            let ua = traits.ua.as_deref().unwrap_or("-");
+            let fingerprint = match is_rare(ua) {
+                true => Fingerprint::RareUa(ua),
+                false => Fingerprint::Behavior {
+                    ua,
+                    referrer: traits.referrer.as_deref().unwrap_or("-"),
+                    urls: traits.sequence(),
+                    timing: traits.timing_bucket(),
+                },
+            };
+            groups.entry(fingerprint).or_default().push((ip, traits));
+        }
+
+        let count_by = |members: &[(&String, &Traits)], key: &dyn Fn(&str) -> Option<String>| {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for value in members.iter().filter_map(|(ip, _)| key(ip)) {
+                *counts.entry(value).or_insert(0) += 1;
+            }
+            let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+            counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            counts
+        };
+
+        let mut clusters: Vec<Cluster> = groups
+            .into_values()
+            .filter(|members| members.len() >= min_size)
+            .map(|members| {
+                let ua = members[0].1.ua.as_deref().unwrap_or("-");
+                let mut urls = members[0].1.sequence().to_vec();
+                for (_, traits) in &members[1..] {
+                    let common = urls
+                        .iter()
+                        .zip(traits.sequence())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    urls.truncate(common);
+                }
+                let timing =
+                    shared(members.iter().map(|(_, traits)| traits.timing_bucket())).flatten();
+                let mut ips: Vec<String> = members.iter().map(|(ip, _)| ip.to_string()).collect();
+                ips.sort_unstable();
+                Cluster {
+                    ua: ua.to_string(),
+                    referrer: shared(
+                        members
+                            .iter()
+                            .map(|(_, traits)| traits.referrer.as_deref().unwrap_or("-")),
+                    )
+                    .map(str::to_string),
+                    urls,
+                    gap_secs: timing.map(|timing| ((1 << timing) - 1, (1 << (timing + 1)) - 2)),
+                    rare_ua: is_rare(ua),
+                    requests: members.iter().map(|(_, traits)| traits.count).sum(),
+                    subnets: count_by(&members, &|ip| Some(subnets.key(ip).into_owned())),
+                    countries: count_by(&members, &|ip| {
+                        grouping.asn(ip).map(|range| range.country.clone())
+                    }),
+                    ips,
+                }
+            })
+            .collect();
+        clusters.sort_unstable_by(|a, b| {
+            b.rare_ua
+                .cmp(&a.rare_ua)
+                .then_with(|| b.ips.len().cmp(&a.ips.len()))
+                .then_with(|| b.requests.cmp(&a.requests))
+                .then_with(|| a.ua.cmp(&b.ua))
+        });
+        clusters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(tracker: &mut ClusterTracker, ip: &str, ua: &str, url: &str, second: i64) {
+        tracker.add(
+            ip,
+            &LogEntry {
+                timestamp: Some(
+                    DateTime::from_timestamp(second, 0)
+                        .unwrap()
+                        .with_timezone(&Local),
+                ),
+                url: Some(url.to_string()),
+                referrer: Some("-".to_string()),
+                ua: Some(ua.to_string()),
+                status: Some(200),
+                bytes: None,
+                duration_ms: None,
+            },
+        );
+    }
+
+    /// 200 IPs of a common browser, so 1 IP of another user agent is rare.
+    fn with_common_traffic() -> ClusterTracker {
+        let mut tracker = ClusterTracker::default();
+        for host in 0..200 {
+            let ip = format!("192.0.2.{host}");
+            request(&mut tracker, &ip, "browser", &format!("/page/{host}"), 0);
+        }
+        tracker
+    }
+
+    fn clusters(tracker: &ClusterTracker, min_size: usize) -> Vec<Cluster> {
+        tracker.clusters(min_size, &Grouping::new(GroupBy::Ip, None))
+    }
+
+    #[test]
+    fn same_rhythm_and_urls_cluster() {
+        let mut tracker = with_common_traffic();
+        for host in 0..5 {
+            let ip = format!("10.0.{host}.1");
+            request(&mut tracker, &ip, "browser", "/a", 0);
+            request(&mut tracker, &ip, "browser", "/b", 10);
+            request(&mut tracker, &ip, "browser", "/c", 20);
+        }
+        let clusters = clusters(&tracker, 5);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].urls, ["/a", "/b", "/c"]);
+        assert_eq!(clusters[0].gap_secs, Some((7, 14)));
+        assert_eq!(clusters[0].referrer.as_deref(), Some("-"));
+        assert_eq!(clusters[0].subnets.len(), 5);
+        assert!(!clusters[0].rare_ua);
+    }
+
+    #[test]
+    fn different_rhythms_split() {
+        let mut tracker = with_common_traffic();
+        for host in 0..6 {
+            let ip = format!("10.0.0.{host}");
+            let gap = if host % 2 == 0 { 10 } else { 1000 };
+            request(&mut tracker, &ip, "browser", "/a", 0);
+            request(&mut tracker, &ip, "browser", "/b", gap);
+        }
+        assert!(clusters(&tracker, 5).is_empty());
+        assert_eq!(clusters(&tracker, 3).len(), 2);
+    }
+
+    #[test]
+    fn ips_without_a_rhythm_cluster_on_the_first_url() {
+        let mut tracker = with_common_traffic();
+        for host in 0..5 {
+            request(
+                &mut tracker,
+                &format!("10.0.0.{host}"),
+                "browser",
+                "/login",
+                0,
+            );
+        }
+        let clusters = clusters(&tracker, 5);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].urls, ["/login"]);
+        assert_eq!(clusters[0].gap_secs, None);
+    }
+
+    #[test]
+    fn rare_user_agent_clusters_by_itself() {
+        let mut tracker = ClusterTracker::default();
+        for host in 0..1000 {
+            let ip = format!("192.0.{}.{}", host / 256, host % 256);
+            request(&mut tracker, &ip, "browser", &format!("/page/{host}"), 0);
+        }
+        for host in 0..5 {
+            let ip = format!("10.0.0.{host}");
+            request(&mut tracker, &ip, "evilbot", "/a", 0);
+            request(
+                &mut tracker,
+                &ip,
+                "evilbot",
+                &format!("/{host}"),
+                host * 100,
+            );
+        }
+        let clusters = clusters(&tracker, 5);
+        assert_eq!(clusters.len(), 1);
+        let cluster = &clusters[0];
+        assert!(cluster.rare_ua);
+        assert_eq!(cluster.ua, "evilbot");
+        assert_eq!(cluster.ips.len(), 5);
+        assert_eq!(cluster.requests, 10);
+        assert_eq!(cluster.urls, ["/a"]);
+        assert_eq!(cluster.gap_secs, None);
+    }
+
+    #[test]
+    fn rare_user_agent_needs_min_size() {
+        let mut tracker = ClusterTracker::default();
+        for host in 0..1000 {
+            let ip = format!("192.0.{}.{}", host / 256, host % 256);
+            request(&mut tracker, &ip, "browser", &format!("/page/{host}"), 0);
+        }
+        for host in 0..4 {
+            request(&mut tracker, &format!("10.0.0.{host}"), "evilbot", "/a", 0);
+        }
+        assert!(clusters(&tracker, 5).is_empty());
+    }
+}
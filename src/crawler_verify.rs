@@ -0,0 +1,165 @@
+use hickory_resolver::{
+    TokioResolver,
+    config::{NameServerConfig, ResolverConfig},
+    net::{NetError, runtime::TokioRuntimeProvider},
+    proto::rr::RData,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    error::Error,
+    net::{IpAddr, SocketAddr},
+};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum Crawler {
+    Google,
+    Bing,
+    Yandex,
+    Apple,
+}
+
+impl Crawler {
+    /// The crawler claimed by a user agent family of the classifier.
+    pub fn from_family(family: &str) -> Option<Self> {
+        match family {
+            "Googlebot" => Some(Self::Google),
+            "Bingbot" => Some(Self::Bing),
+            "YandexBot" => Some(Self::Yandex),
+            "Applebot" => Some(Self::Apple),
+            _ => None,
+        }
+    }
+
+    /// Domains the reverse DNS name of the crawler's addresses ends with.
+    fn domains(&self) -> &'static [&'static str] {
+        match self {
+            // not all of googleusercontent.com, any GCP VM has a PTR record there
+            Self::Google => &["googlebot.com", "google.com", "gae.googleusercontent.com"],
+            Self::Bing => &["search.msn.com"],
+            Self::Yandex => &["yandex.ru", "yandex.net", "yandex.com"],
+            Self::Apple => &["applebot.apple.com"],
+        }
+    }
+
+    fn owns(&self, hostname: &str) -> bool {
+        self.domains().iter().any(|domain| {
+            hostname == *domain
+                || hostname
+                    .strip_suffix(domain)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verification {
+    Verified,
+    Spoofed,
+    /// The DNS server failed or timed out
+    Unresolved,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CrawlerCheck {
+    pub crawler: Crawler,
+    pub status: Verification,
+    pub hostname: Option<String>,
+}
+
+/// Forward-confirmed reverse DNS of IPs claiming to be a search engine crawler.
+pub struct CrawlerVerifier {
+    resolver: TokioResolver,
+}
+
+impl CrawlerVerifier {
+    /// Uses the given DNS server, or the system configuration.
+    pub fn new(server: Option<SocketAddr>) -> Result<Self, Box<dyn Error>> {
+        let resolver = match server {
+            Some(server) => {
+                let mut name_server = NameServerConfig::udp_and_tcp(server.ip());
+                for connection in &mut name_server.connections {
+                    connection.port = server.port();
+                }
+                TokioResolver::builder_with_config(
+                    ResolverConfig::from_name_servers(vec![name_server]),
+                    TokioRuntimeProvider::default(),
+                )
+                .build()?
+            }
+            None => TokioResolver::builder_tokio()?.build()?,
+        };
+        Ok(Self { resolver })
+    }
+
+    pub async fn verify(&self, ip: &str, crawler: Crawler) -> CrawlerCheck {
+        let check = |status, hostname| CrawlerCheck {
+            crawler,
+            status,
+            hostname,
+        };
+        let failed = |err: NetError| match err.is_no_records_found() {
+            true => Verification::Spoofed,
+            false => Verification::Unresolved,
+        };
+        let Ok(address) = ip.parse::<IpAddr>() else {
+            return check(Verification::Unresolved, None);
+        };
+
+        let hostnames: Vec<String> = match self.resolver.reverse_lookup(address).await {
+            Ok(lookup) => lookup
+                .answers()
+                .iter()
+                .filter_map(|record| match &record.data {
+                    RData::PTR(ptr) => Some(ptr.to_string().trim_end_matches('.').to_lowercase()),
+                    _ => None,
+                })
+                .collect(),
+            Err(err) => return check(failed(err), None),
+        };
+        let Some(hostname) = hostnames.iter().find(|hostname| crawler.owns(hostname)) else {
+            return check(Verification::Spoofed, hostnames.into_iter().next());
+        };
+
+        // the crawler's domain must resolve back to the same address
+        match self.resolver.lookup_ip(format!("{hostname}.")).await {
+            Ok(lookup) if lookup.iter().any(|resolved| resolved == address) => {
+                check(Verification::Verified, Some(hostname.clone()))
+            }
+            Ok(_) => check(Verification::Spoofed, Some(hostname.clone())),
+            Err(err) => check(failed(err), Some(hostname.clone())),
+        }
+    }
+
+    /// Verifies the claimed crawler of each key's IP in parallel.
+    pub async fn verify_all(
+        &self,
+        claims: Vec<(String, String, Crawler)>,
+    ) -> Result<HashMap<String, CrawlerCheck>, Box<dyn Error>> {
+        let tasks: Vec<_> = claims
+            .into_iter()
+            .map(|(key, ip, crawler)| {
+                let verifier = Self {
+                    resolver: self.resolver.clone(),
+                };
+                tokio::task::spawn(async move { (key, verifier.verify(&ip, crawler).await) })
+            })
+            .collect();
+
+        let mut results = HashMap::new();
+        for task in tasks {
+            let (key, check) = task.await?;
+            results.insert(key, check);
+        }
+        Ok(results)
+    }
+}
+
+/// Parses `IP` or `IP:PORT` of a DNS server, the port defaults to 53.
+pub fn parse_dns_server(value: &str) -> Result<SocketAddr, String> {
+    value
+        .parse::<SocketAddr>()
+        .or_else(|_| value.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+        .map_err(|_| format!("'{value}' is not an IP address or IP:PORT"))
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+use crate::{
+    ip_info::IpInfo,
+    report::{DimensionReport, GlobalReport},
+};
+
+/// The parts of a report that are compared, loaded from a saved JSON report
+/// or built from a fresh analysis.
+#[derive(Deserialize, Debug, Default)]
+pub struct Snapshot {
+    pub ips: Vec<SnapshotEntry>,
+    #[serde(default)]
+    pub global: Option<SnapshotGlobal>,
+    /// A saved report only holds the rows it printed, values it lacks are unknown
+    #[serde(skip)]
+    pub partial: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SnapshotEntry {
+    pub ip: String,
+    pub count: usize,
+    #[serde(default)]
+    pub total_bytes: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SnapshotGlobal {
+    pub urls: Vec<SnapshotValue>,
+    pub uas: Vec<SnapshotValue>,
+    pub referrer_domains: Vec<SnapshotValue>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SnapshotValue {
+    pub value: String,
+    pub count: usize,
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let json = fs::read_to_string(path)?;
+        let snapshot: Self = serde_json::from_str(&json)
+            .map_err(|err| format!("{} is not a saved report: {err}", path.display()))?;
+        Ok(Self {
+            partial: true,
+            ..snapshot
+        })
+    }
+
+    pub fn new(ip_map: &HashMap<String, IpInfo>, global: Option<&GlobalReport>) -> Self {
+        let values = |reports: &[DimensionReport]| {
+            reports
+                .iter()
+                .map(|report| SnapshotValue {
+                    value: report.value.clone(),
+                    count: report.count,
+                })
+                .collect()
+        };
+        Self {
+            ips: ip_map
+                .iter()
+                .map(|(ip, info)| SnapshotEntry {
+                    ip: ip.clone(),
+                    count: info.count,
+                    total_bytes: info.total_bytes,
+                })
+                .collect(),
+            global: global.map(|global| SnapshotGlobal {
+                urls: values(&global.urls),
+                uas: values(&global.uas),
+                referrer_domains: values(&global.referrer_domains),
+            }),
+            partial: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiffEntry {
+    pub value: String,
+    pub change: Change,
+    pub old: usize,
+    pub new: usize,
+    pub delta: i64,
+    pub old_bytes: Option<u64>,
+    pub new_bytes: Option<u64>,
+}
+
+/// Added, removed and changed entries between two snapshots, largest moves first.
+/// Entries with the same count on both sides are left out. Against a partial
+/// snapshot only the values it holds are compared, and the global dimensions
+/// only when both snapshots have them.
+#[derive(Serialize, Debug)]
+pub struct SnapshotDiff {
+    pub ips: Vec<DiffEntry>,
+    pub urls: Option<Vec<DiffEntry>>,
+    pub uas: Option<Vec<DiffEntry>>,
+    pub referrer_domains: Option<Vec<DiffEntry>>,
+}
+
+impl SnapshotDiff {
+    pub fn new(old: &Snapshot, new: &Snapshot) -> Self {
+        let ip_bytes = |snapshot: &Snapshot| -> HashMap<String, (usize, Option<u64>)> {
+            snapshot
+                .ips
+                .iter()
+                .map(|entry| (entry.ip.clone(), (entry.count, Some(entry.total_bytes))))
+                .collect()
+        };
+        let global = |field: fn(&SnapshotGlobal) -> &Vec<SnapshotValue>| {
+            let (Some(old_global), Some(new_global)) = (&old.global, &new.global) else {
+                return None;
+            };
+            let values = |global| -> HashMap<String, (usize, Option<u64>)> {
+                field(global)
+                    .iter()
+                    .map(|value| (value.value.clone(), (value.count, None)))
+                    .collect()
+            };
+            Some(Self::compare(values(old_global), values(new_global), old, new))
+        };
+        Self {
+            ips: Self::compare(ip_bytes(old), ip_bytes(new), old, new),
+            urls: global(|global| &global.urls),
+            uas: global(|global| &global.uas),
+            referrer_domains: global(|global| &global.referrer_domains),
+        }
+    }
+
+    fn compare(
+        mut old: HashMap<String, (usize, Option<u64>)>,
+        new: HashMap<String, (usize, Option<u64>)>,
+        old_snapshot: &Snapshot,
+        new_snapshot: &Snapshot,
+    ) -> Vec<DiffEntry> {
+        let mut entries = Vec::new();
+        for (value, (new_count, new_bytes)) in new {
+            let (change, old_count, old_bytes) = match old.remove(&value) {
+                Some((old_count, _)) if old_count == new_count => continue,
+                Some((old_count, old_bytes)) => (Change::Changed, old_count, old_bytes),
+                // the value may just be missing from the saved rows
+                None if old_snapshot.partial => continue,
+                None => (Change::Added, 0, None),
+            };
+            entries.push(DiffEntry {
+                value,
+                change,
+                old: old_count,
+                new: new_count,
+                delta: new_count as i64 - old_count as i64,
+                old_bytes,
+                new_bytes,
+            });
+        }
+        for (value, (old_count, old_bytes)) in old {
+            if new_snapshot.partial {
+                continue;
+            }
+            entries.push(DiffEntry {
+                value,
+                change: Change::Removed,
+                old: old_count,
+                new: 0,
+                delta: -(old_count as i64),
+                old_bytes,
+                new_bytes: None,
+            });
+        }
+        entries.sort_unstable_by(|a, b| {
+            b.delta
+                .abs()
+                .cmp(&a.delta.abs())
+                .then_with(|| a.value.cmp(&b.value))
+        });
+        entries
+    }
+}
@@ -21,13 +21,15 @@ impl FileReader {
         Ok(Self { file })
     }
 
-    pub fn get_lines(&mut self, dir: ReaderDirection) -> Result<impl Iterator<Item = io::Result<String>>, io::Error> {
+    pub fn get_lines(&mut self, dir: ReaderDirection) -> Result<impl Iterator<Item = io::Result<String>> + use<>, io::Error> {
 
         self.file.rewind()?;
 
+        // the reader owns its own handle so the caller stays free to borrow self
+        let file = self.file.try_clone()?;
         let reader: Box<dyn BufRead> = match dir {
-            ReaderDirection::Normal => Box::new(BufReader::new(&self.file)),
-            ReaderDirection::Reverse => Box::new(RevBufReader::new(&self.file)),
+            ReaderDirection::Normal => Box::new(BufReader::new(file)),
+            ReaderDirection::Reverse => Box::new(RevBufReader::new(file)),
         };
         
         Ok(reader.lines())
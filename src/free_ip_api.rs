@@ -22,12 +22,7 @@ impl FreeIpApi {
             .map(|ip_clone| {
                 tokio::task::spawn(async move {
                     // Fetch the location info
-                    if let Ok(info) = FreeIpApi::fetch_ip_details(&ip_clone).await {
-                        // Create and return the IpLocation including the IP
-                        Some(info)
-                    } else {
-                        None
-                    }
+                    FreeIpApi::fetch_ip_details(&ip_clone).await.ok()
                 })
             })
             .collect();
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{log_entry::LogEntry, ranking::SortKey, sketch::TDigest};
+
+#[derive(Debug, Default)]
+pub struct DimensionStat {
+    pub count: usize,
+    pub ips: HashSet<String>,
+    pub bytes: u64,
+    pub max_bytes: u64,
+}
+
+impl DimensionStat {
+    fn add(&mut self, ip: &str, bytes: u64) {
+        self.count += 1;
+        self.bytes += bytes;
+        self.max_bytes = self.max_bytes.max(bytes);
+        if !self.ips.contains(ip) {
+            self.ips.insert(ip.to_string());
+        }
+    }
+}
+
+/// Request counts across all clients, independent of the selected IPs.
+#[derive(Debug, Default)]
+pub struct GlobalStats {
+    pub urls: HashMap<String, DimensionStat>,
+    pub uas: HashMap<String, DimensionStat>,
+    pub referrer_domains: HashMap<String, DimensionStat>,
+    /// Request durations per URL in milliseconds
+    pub url_latency: HashMap<String, TDigest>,
+}
+
+impl GlobalStats {
+    pub fn collect(&mut self, ip: &str, entry: &LogEntry) {
+        let bytes = entry.bytes.unwrap_or(0);
+        if let Some(url) = &entry.url {
+            Self::add(&mut self.urls, url, ip, bytes);
+            if let Some(duration_ms) = entry.duration_ms {
+                match self.url_latency.get_mut(url) {
+                    Some(digest) => digest.add(duration_ms),
+                    None => self
+                        .url_latency
+                        .entry(url.clone())
+                        .or_default()
+                        .add(duration_ms),
+                }
+            }
+        }
+        if let Some(ua) = &entry.ua {
+            Self::add(&mut self.uas, ua, ip, bytes);
+        }
+        if let Some(domain) = entry.referrer.as_deref().and_then(referrer_domain) {
+            Self::add(&mut self.referrer_domains, domain, ip, bytes);
+        }
+    }
+
+    fn add(map: &mut HashMap<String, DimensionStat>, key: &str, ip: &str, bytes: u64) {
+        match map.get_mut(key) {
+            Some(stat) => stat.add(ip, bytes),
+            None => map.entry(key.to_string()).or_default().add(ip, bytes),
+        }
+    }
+
+    pub fn top(
+        map: &HashMap<String, DimensionStat>,
+        limit: usize,
+        sort: SortKey,
+    ) -> Vec<(&String, &DimensionStat)> {
+        let mut vec: Vec<(&String, &DimensionStat)> = map.iter().collect();
+        vec.sort_unstable_by(|a, b| {
+            let (a_value, b_value) = match sort {
+                SortKey::Bytes => (a.1.bytes, b.1.bytes),
+                _ => (a.1.count as u64, b.1.count as u64),
+            };
+            b_value.cmp(&a_value).then_with(|| a.0.cmp(b.0))
+        });
+        vec.truncate(limit);
+        vec
+    }
+}
+
+/// Extracts the host from a referrer URL, `-` and empty referrers have none.
+fn referrer_domain(referrer: &str) -> Option<&str> {
+    let without_scheme = match referrer.split_once("://") {
+        Some((_, rest)) => rest,
+        None => referrer,
+    };
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    };
+
+    match host {
+        "" | "-" => None,
+        host => Some(host),
+    }
+}
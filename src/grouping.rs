@@ -0,0 +1,210 @@
+use clap::ValueEnum;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum GroupBy {
+    #[default]
+    Ip,
+    /// IPv4 /24 network (IPv6 /48)
+    #[value(name = "/24")]
+    Slash24,
+    /// IPv4 /16 network (IPv6 /32)
+    #[value(name = "/16")]
+    Slash16,
+    /// Autonomous system, requires --asn-db
+    Asn,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsnRange {
+    pub start: u128,
+    pub end: u128,
+    pub asn: u32,
+    pub country: String,
+    pub description: String,
+}
+
+/// IP to ASN database in the iptoasn.com TSV format:
+/// `range_start  range_end  AS_number  country_code  AS_description`
+#[derive(Debug, Default)]
+pub struct AsnDb {
+    ranges: Vec<AsnRange>,
+}
+
+impl AsnDb {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut ranges = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 5 {
+                continue;
+            }
+            let (Ok(start), Ok(end), Ok(asn)) = (
+                fields[0].parse::<IpAddr>(),
+                fields[1].parse::<IpAddr>(),
+                fields[2].parse::<u32>(),
+            ) else {
+                continue;
+            };
+            if asn == 0 {
+                // "Not routed"
+                continue;
+            }
+            ranges.push(AsnRange {
+                start: ip_to_u128(&start),
+                end: ip_to_u128(&end),
+                asn,
+                country: fields[3].to_string(),
+                description: fields[4].to_string(),
+            });
+        }
+        ranges.sort_unstable_by_key(|range| range.start);
+
+        Ok(Self { ranges })
+    }
+
+    pub fn lookup(&self, ip: &IpAddr) -> Option<&AsnRange> {
+        let ip = ip_to_u128(ip);
+        let idx = self.ranges.partition_point(|range| range.start <= ip);
+        self.ranges[..idx].last().filter(|range| ip <= range.end)
+    }
+}
+
+/// Maps IPv4 addresses into the IPv4-mapped IPv6 space so both families share one range table.
+pub fn ip_to_u128(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(v4) => u128::from(v4.to_ipv6_mapped()),
+        IpAddr::V6(v6) => u128::from(*v6),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Grouping {
+    group_by: GroupBy,
+    asn_db: Option<AsnDb>,
+}
+
+impl Grouping {
+    pub fn new(group_by: GroupBy, asn_db: Option<AsnDb>) -> Self {
+        Self { group_by, asn_db }
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        self.group_by != GroupBy::Ip
+    }
+
+    pub fn asn(&self, ip: &str) -> Option<&AsnRange> {
+        let addr = ip.parse::<IpAddr>().ok()?;
+        self.asn_db.as_ref()?.lookup(&addr)
+    }
+
+    /// Returns the key the given IP is aggregated under.
+    pub fn key<'a>(&self, ip: &'a str) -> Cow<'a, str> {
+        if self.group_by == GroupBy::Ip {
+            return Cow::Borrowed(ip);
+        }
+        let Ok(addr) = ip.parse::<IpAddr>() else {
+            return Cow::Borrowed(ip);
+        };
+
+        match (self.group_by, addr) {
+            (GroupBy::Slash24, IpAddr::V4(v4)) => Cow::Owned(format!("{}/24", mask_v4(v4, 24))),
+            (GroupBy::Slash16, IpAddr::V4(v4)) => Cow::Owned(format!("{}/16", mask_v4(v4, 16))),
+            (GroupBy::Slash24, IpAddr::V6(v6)) => Cow::Owned(format!("{}/48", mask_v6(v6, 48))),
+            (GroupBy::Slash16, IpAddr::V6(v6)) => Cow::Owned(format!("{}/32", mask_v6(v6, 32))),
+            (GroupBy::Asn, addr) => match self.asn_db.as_ref().and_then(|db| db.lookup(&addr)) {
+                Some(range) => Cow::Owned(format!("AS{} {}", range.asn, range.description)),
+                None => Cow::Borrowed("AS? unknown"),
+            },
+            (GroupBy::Ip, _) => Cow::Borrowed(ip),
+        }
+    }
+}
+
+fn mask_v4(ip: Ipv4Addr, prefix: u32) -> Ipv4Addr {
+    Ipv4Addr::from(u32::from(ip) & (u32::MAX << (32 - prefix)))
+}
+
+fn mask_v6(ip: Ipv6Addr, prefix: u32) -> Ipv6Addr {
+    Ipv6Addr::from(u128::from(ip) & (u128::MAX << (128 - prefix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(group_by: GroupBy, ip: &str) -> String {
+        Grouping::new(group_by, None).key(ip).into_owned()
+    }
+
+    fn asn_db() -> AsnDb {
+        let range = |start: &str, end: &str, asn, description: &str| AsnRange {
+            start: ip_to_u128(&start.parse().unwrap()),
+            end: ip_to_u128(&end.parse().unwrap()),
+            asn,
+            country: "US".to_string(),
+            description: description.to_string(),
+        };
+        AsnDb {
+            ranges: vec![
+                range("8.8.8.0", "8.8.8.255", 15169, "GOOGLE"),
+                range(
+                    "2600:1f00::",
+                    "2600:1fff:ffff:ffff:ffff:ffff:ffff:ffff",
+                    16509,
+                    "AMAZON",
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn ip_keys_are_unchanged() {
+        assert_eq!(key(GroupBy::Ip, "192.0.2.77"), "192.0.2.77");
+        assert!(!Grouping::new(GroupBy::Ip, None).is_grouped());
+    }
+
+    #[test]
+    fn ipv4_networks() {
+        assert_eq!(key(GroupBy::Slash24, "192.0.2.77"), "192.0.2.0/24");
+        assert_eq!(key(GroupBy::Slash16, "192.0.2.77"), "192.0.0.0/16");
+        assert_eq!(key(GroupBy::Slash24, "10.255.255.255"), "10.255.255.0/24");
+    }
+
+    #[test]
+    fn ipv6_networks() {
+        assert_eq!(
+            key(GroupBy::Slash24, "2001:db8:aaaa:bbbb::1"),
+            "2001:db8:aaaa::/48"
+        );
+        assert_eq!(
+            key(GroupBy::Slash16, "2001:db8:aaaa:bbbb::1"),
+            "2001:db8::/32"
+        );
+    }
+
+    #[test]
+    fn unparsable_ips_are_their_own_key() {
+        assert_eq!(key(GroupBy::Slash24, "unknown"), "unknown");
+        assert_eq!(key(GroupBy::Asn, "unknown"), "unknown");
+    }
+
+    #[test]
+    fn asn_keys() {
+        let grouping = Grouping::new(GroupBy::Asn, Some(asn_db()));
+        assert_eq!(grouping.key("8.8.8.8"), "AS15169 GOOGLE");
+        assert_eq!(grouping.key("2600:1f18::1"), "AS16509 AMAZON");
+        assert_eq!(grouping.key("8.8.9.1"), "AS? unknown");
+        assert_eq!(grouping.key("8.8.7.255"), "AS? unknown");
+        assert_eq!(grouping.asn("8.8.8.0").map(|range| range.asn), Some(15169));
+    }
+}
@@ -1,6 +1,6 @@
-use chrono::{DateTime, Duration, Local};
+use chrono::{DateTime, Duration, Local, TimeDelta};
 use std::collections::HashMap;
-use crate::{ip_location::IpLocation, log_entry::LogEntry};
+use crate::{crawler_verify::CrawlerCheck, ip_location::IpLocation, log_entry::LogEntry, query_params::ParamUsage, scoring::Score, sessions::Hit, sketch::TDigest, traps::TrapHit, ua_rotation::UaHistory};
 
 #[derive(Debug, Clone)]
 pub struct IpInfo {
@@ -10,6 +10,26 @@ pub struct IpInfo {
     pub referrer_map: HashMap<String, usize>,
     pub timestamps: Vec<DateTime<Local>>,
     pub location_data: Option<IpLocation>,
+    pub members: HashMap<String, usize>,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub status_map: HashMap<u16, usize>,
+    pub hits: Vec<Hit>,
+    /// Request durations in milliseconds
+    pub latency: TDigest,
+    pub params: HashMap<String, ParamUsage>,
+    pub crawler_check: Option<CrawlerCheck>,
+    /// Matched attack rule ids and their request counts
+    pub rule_hits: HashMap<String, usize>,
+    pub score: Option<Score>,
+    /// URLs answered with 404
+    pub not_found_map: HashMap<String, usize>,
+    pub ua_history: UaHistory,
+    pub trap: Option<TrapHit>,
+    /// Raw URLs the robots.txt group of the request's user agent disallows
+    pub disallowed_map: HashMap<String, usize>,
+    /// Names of the reputation lists containing the IP or one of its members
+    pub reputation: Vec<String>,
 }
 impl IpInfo {
     pub fn new() -> Self {
@@ -20,6 +40,21 @@ impl IpInfo {
             referrer_map: HashMap::new(),
             timestamps: Vec::new(),
             location_data: None,
+            members: HashMap::new(),
+            total_bytes: 0,
+            max_bytes: 0,
+            status_map: HashMap::new(),
+            hits: Vec::new(),
+            latency: TDigest::default(),
+            params: HashMap::new(),
+            crawler_check: None,
+            rule_hits: HashMap::new(),
+            score: None,
+            not_found_map: HashMap::new(),
+            ua_history: UaHistory::default(),
+            trap: None,
+            disallowed_map: HashMap::new(),
+            reputation: Vec::new(),
         }
     }
 
@@ -27,8 +62,50 @@ impl IpInfo {
         self.count += 1;
     }
 
+    pub fn add_bytes(&mut self, bytes: u64) {
+        self.total_bytes += bytes;
+        self.max_bytes = self.max_bytes.max(bytes);
+    }
+
+    pub fn add_member(&mut self, ip: &str) {
+        *self.members.entry(ip.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn record_hit(&mut self, ip: &str, entry: &LogEntry) {
+        if let Some(hit) = Hit::from_entry(ip, entry) {
+            self.hits.push(hit);
+        }
+    }
+
+    pub fn collect_params(&mut self, params: &[(String, String)]) {
+        for (name, value) in params {
+            self.params.entry(name.clone()).or_default().add(value);
+        }
+    }
+
+    pub fn add_rule_hits(&mut self, rule_ids: Vec<String>) {
+        for id in rule_ids {
+            *self.rule_hits.entry(id).or_insert(0) += 1;
+        }
+    }
+
+    pub fn add_disallowed(&mut self, url: String) {
+        *self.disallowed_map.entry(url).or_insert(0) += 1;
+    }
+
+    pub fn track_ua(&mut self, entry: &LogEntry, session_gap: TimeDelta) {
+        if let (Some(ua), Some(timestamp)) = (&entry.ua, entry.timestamp) {
+            self.ua_history.add(ua, timestamp, session_gap);
+        }
+    }
+
     pub fn collect_entry(&mut self, info: LogEntry) {
         //self.count += 1;
+        if info.status == Some(404)
+            && let Some(url) = &info.url
+        {
+            *self.not_found_map.entry(url.clone()).or_insert(0) += 1;
+        }
         if let Some(ua) = info.ua {
             let map = self.ua_map.entry(ua).or_insert(0);
             *map += 1;
@@ -45,6 +122,20 @@ impl IpInfo {
         if let Some(timestamp) = info.timestamp {
             self.timestamps.push(timestamp);
         }
+        if let Some(status) = info.status {
+            *self.status_map.entry(status).or_insert(0) += 1;
+        }
+        if let Some(duration_ms) = info.duration_ms {
+            self.latency.add(duration_ms);
+        }
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.status_map
+            .iter()
+            .filter(|&(&status, _)| status >= 400)
+            .map(|(_, count)| count)
+            .sum()
     }
 
     pub fn average_rpm(&self) -> f64 {
@@ -91,6 +182,15 @@ impl IpInfo {
         recent_timestamps.len() as f64 / duration_in_minutes as f64
     }
 
+    /// Most requests within a single minute.
+    pub fn peak_rpm(&self) -> usize {
+        let mut minutes: HashMap<i64, usize> = HashMap::new();
+        for timestamp in &self.timestamps {
+            *minutes.entry(timestamp.timestamp().div_euclid(60)).or_insert(0) += 1;
+        }
+        minutes.into_values().max().unwrap_or(0)
+    }
+
     pub fn last_timestamp(&self) -> Option<DateTime<Local>> {
         self.timestamps.iter().max().cloned()
     }
@@ -7,6 +7,10 @@ pub struct LogEntry {
     pub url: Option<String>,
     pub referrer: Option<String>,
     pub ua: Option<String>,
+    pub status: Option<u16>,
+    pub bytes: Option<u64>,
+    /// Request duration in milliseconds
+    pub duration_ms: Option<f64>,
 }
 
 impl LogEntry {
@@ -20,17 +24,17 @@ impl LogEntry {
         if let Some((_found_ip, rest_of_line)) = line.split_once(" ") {
             //ip = Some(found_ip.to_string());
 
-            if let Some((date, _)) = rest_of_line.split_once("]") {
-                if let Some((_, date)) = date.split_once("[") {
-                    timestamp = DateTime::parse_from_str(date, "%d/%b/%Y:%H:%M:%S %z")
-                        .ok()
-                        .map(|dt| dt.with_timezone(&Local));
-                }
+            if let Some((date, _)) = rest_of_line.split_once("]")
+                && let Some((_, date)) = date.split_once("[")
+            {
+                timestamp = DateTime::parse_from_str(date, "%d/%b/%Y:%H:%M:%S %z")
+                    .ok()
+                    .map(|dt| dt.with_timezone(&Local));
             }
         }
 
         let parts: Vec<&str> = line.split('"').collect();
-        if parts.len() >= 5 {
+        if parts.len() >= 6 {
             url = Some(parts[1].to_string());
             referrer = Some(parts[3].to_string());
             ua = Some(parts[5].to_string());
@@ -41,9 +45,71 @@ impl LogEntry {
             url,
             referrer,
             ua,
+            status: Self::parse_status(line),
+            bytes: Self::parse_bytes(line),
+            duration_ms: Self::parse_duration_ms(line),
         })
     }
 
+    pub fn parse_url(line: &str) -> Option<&str> {
+        line.split('"').nth(1)
+    }
+
+    pub fn parse_status(line: &str) -> Option<u16> {
+        let status_and_bytes = line.split('"').nth(2)?;
+        status_and_bytes.split_whitespace().next()?.parse().ok()
+    }
+
+    /// Response size (`%b` / `$body_bytes_sent`), `-` means no body.
+    pub fn parse_bytes(line: &str) -> Option<u64> {
+        let status_and_bytes = line.split('"').nth(2)?;
+        match status_and_bytes.split_whitespace().nth(1)? {
+            "-" => Some(0),
+            bytes => bytes.parse().ok(),
+        }
+    }
+
+    /// Request duration from the fields after the user agent. Understands
+    /// `rt=`/`request_time=` and `urt=`/`upstream_response_time=` pairs as well
+    /// as bare values, where a decimal is taken as seconds (`$request_time`)
+    /// and an integer as microseconds (`%D`). The request time wins over the
+    /// upstream time, multiple upstream times are summed.
+    pub fn parse_duration_ms(line: &str) -> Option<f64> {
+        let rest = line.splitn(7, '"').nth(6)?;
+
+        let mut request_time = None;
+        let mut upstream_time = None;
+        // a value list like `urt="0.1, 0.2"` continues after a trailing comma
+        let mut continued_key = None;
+        for token in rest.split_whitespace() {
+            let (key, value) = match continued_key.take() {
+                Some(key) => (key, token),
+                None => token.split_once('=').unwrap_or(("", token)),
+            };
+            if value.ends_with(',') {
+                continued_key = Some(key);
+            }
+            let value = value.trim_matches(['"', ',']);
+            match key {
+                "rt" | "request_time" => request_time = parse_seconds(value),
+                // `-` for cached and static responses, skipped instead of losing `rt`
+                "urt" | "upstream_response_time" => {
+                    if let Some(seconds) = parse_seconds(value) {
+                        upstream_time = Some(upstream_time.unwrap_or(0.0) + seconds);
+                    }
+                }
+                "" if request_time.is_none() => {
+                    request_time = match value.contains('.') {
+                        true => parse_seconds(value),
+                        false => value.parse::<u64>().ok().map(|us| us as f64 / 1000.0),
+                    };
+                }
+                _ => {}
+            }
+        }
+        request_time.or(upstream_time)
+    }
+
     pub fn parse_ip(line: &str) -> Option<&str> {
         let mut ip: Option<&str> = None;
         if let Some((found_ip, _)) = line.split_once(" ") {
@@ -53,16 +119,69 @@ impl LogEntry {
     }
 
     pub fn parse_ip_and_timestamp(line: &str) -> Option<(&str, DateTime<Local>)> {
-        if let Some((found_ip, rest_of_line)) = line.split_once(" ") {
-            if let Some((date, _)) = rest_of_line.split_once("]") {
-                if let Some((_, date_str)) = date.split_once("[") {
-                    if let Ok(dt) = DateTime::parse_from_str(date_str, "%d/%b/%Y:%H:%M:%S %z") {
-                        let timestamp = dt.with_timezone(&Local);
-                        return Some((found_ip, timestamp));
-                    }
-                }
-            }
+        if let Some((found_ip, rest_of_line)) = line.split_once(" ")
+            && let Some((date, _)) = rest_of_line.split_once("]")
+            && let Some((_, date_str)) = date.split_once("[")
+            && let Ok(dt) = DateTime::parse_from_str(date_str, "%d/%b/%Y:%H:%M:%S %z")
+        {
+            let timestamp = dt.with_timezone(&Local);
+            return Some((found_ip, timestamp));
         }
         None // Return None if parsing fails
     }
 }
+
+fn parse_seconds(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().map(|secs| secs * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = r#"1.2.3.4 - - [08/Oct/2024:00:00:00 +0000] "GET / HTTP/1.1" 200 100 "-" "curl/8.0""#;
+
+    fn duration(fields: &str) -> Option<f64> {
+        LogEntry::parse_duration_ms(&format!("{LINE} {fields}"))
+    }
+
+    #[test]
+    fn request_time_pair() {
+        assert_eq!(duration("rt=0.123"), Some(123.0));
+        assert_eq!(duration("request_time=1.5"), Some(1500.0));
+    }
+
+    #[test]
+    fn request_time_wins_over_upstream() {
+        assert_eq!(duration("rt=0.250 urt=0.200"), Some(250.0));
+    }
+
+    #[test]
+    fn upstream_list_is_summed() {
+        let ms = duration(r#"urt="0.1, 0.2""#).unwrap();
+        assert!((ms - 300.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unparsable_upstream_keeps_request_time() {
+        assert_eq!(duration("rt=0.123 urt=-"), Some(123.0));
+        assert_eq!(duration(r#"urt="-, 0.2""#), Some(200.0));
+    }
+
+    #[test]
+    fn bare_decimal_is_seconds() {
+        assert_eq!(duration("0.042"), Some(42.0));
+    }
+
+    #[test]
+    fn bare_integer_is_microseconds() {
+        assert_eq!(duration("1500"), Some(1.5));
+    }
+
+    #[test]
+    fn dash_has_no_duration() {
+        assert_eq!(duration("-"), None);
+        assert_eq!(duration("rt=- urt=-"), None);
+        assert_eq!(LogEntry::parse_duration_ms(LINE), None);
+    }
+}
@@ -1,4 +1,21 @@
-use crate::{file_reader::{FileReader, ReaderDirection}, ip_info::IpInfo, log_entry::LogEntry};
+use crate::{
+    anomaly::WindowComparison,
+    clusters::ClusterTracker,
+    file_reader::{FileReader, ReaderDirection},
+    global_stats::GlobalStats,
+    grouping::Grouping,
+    ip_info::IpInfo,
+    log_entry::LogEntry,
+    login::LoginDetector,
+    query_params::{ParamStats, parse_query},
+    robots::Robots,
+    rules::RuleSet,
+    scanning::MissingPaths,
+    sketch::Sketches,
+    timeline::TrafficTimeline,
+    traps::TrapPaths,
+    url_normalizer::UrlNormalizer,
+};
 use chrono::{DateTime, Local, TimeDelta};
 use std::{ collections::{HashMap, HashSet}, io::Error, path::Path};
 
@@ -17,6 +34,28 @@ pub struct LogProcessor {
     most_recent_timestamp: DateTime<Local>,
     break_line: Option<String>,
     pub filter_ips: HashSet<String>,
+    pub traffic: Option<TrafficTimeline>,
+    pub grouping: Grouping,
+    pub global_stats: Option<GlobalStats>,
+    /// Keep every request of the tracked IPs in order, needed for sessions.
+    pub track_hits: bool,
+    /// Follow the user agents of the tracked IPs, sessions split after this idle time.
+    pub ua_session_gap: Option<TimeDelta>,
+    /// Count IPs in bounded memory sketches instead of `ip_map` during the first pass.
+    pub sketches: Option<Sketches>,
+    pub url_normalizer: Option<UrlNormalizer>,
+    /// Analyze query string parameters, taken from the URL before normalization.
+    pub param_stats: Option<ParamStats>,
+    pub comparison: Option<WindowComparison>,
+    /// Attack signatures matched against the raw entries of tracked IPs.
+    pub rules: Option<RuleSet>,
+    pub logins: Option<LoginDetector>,
+    pub missing_paths: Option<MissingPaths>,
+    /// Traits of every IP, including those below the filters, for botnet clustering.
+    pub clusters: Option<ClusterTracker>,
+    pub traps: Option<TrapPaths>,
+    /// Checked against the raw URLs of tracked IPs, before --collapse-ids and rewrites.
+    pub robots: Option<Robots>,
 }
 
 impl LogProcessor {
@@ -28,6 +67,21 @@ impl LogProcessor {
             break_line: None,
             most_recent_timestamp: DateTime::default(),
             filter_ips: HashSet::new(),
+            traffic: None,
+            grouping: Grouping::default(),
+            global_stats: None,
+            track_hits: false,
+            ua_session_gap: None,
+            sketches: None,
+            url_normalizer: None,
+            param_stats: None,
+            comparison: None,
+            rules: None,
+            logins: None,
+            missing_paths: None,
+            clusters: None,
+            traps: None,
+            robots: None,
         })
     }
 
@@ -35,6 +89,75 @@ impl LogProcessor {
         self.most_recent_timestamp
     }
 
+    fn count_ip(
+        &mut self,
+        ip_map: &mut HashMap<String, IpInfo>,
+        ip: &str,
+        line: &str,
+        timestamp: Option<DateTime<Local>>,
+    ) {
+        let key = self.grouping.key(ip);
+        // Traps are only checked here, in the first pass. With --filter-hours this
+        // includes the first out-of-window line, which is counted before the break.
+        if let Some(traps) = &mut self.traps
+            && let Some(request) = LogEntry::parse_url(line)
+        {
+            let timestamp = timestamp.or_else(|| LogEntry::parse_ip_and_timestamp(line).map(|(_, ts)| ts));
+            traps.check(&key, request, timestamp);
+        }
+        if let Some(sketches) = &mut self.sketches {
+            let timestamp = timestamp.or_else(|| LogEntry::parse_ip_and_timestamp(line).map(|(_, ts)| ts));
+            sketches.observe(&key, line, timestamp);
+            return;
+        }
+        let entry = ip_map.entry(key.into_owned()).or_insert_with(IpInfo::new);
+        Self::count_entry(entry, ip, line, self.grouping.is_grouped());
+    }
+
+    fn count_entry(entry: &mut IpInfo, ip: &str, line: &str, grouped: bool) {
+        entry.increment();
+        if let Some(bytes) = LogEntry::parse_bytes(line) {
+            entry.add_bytes(bytes);
+        }
+        if grouped {
+            entry.add_member(ip);
+        }
+    }
+
+    /// Whether any global aggregation needs entries of IPs outside `filter_ips`.
+    fn collects_all_entries(&self) -> bool {
+        self.traffic.is_some()
+            || self.global_stats.is_some()
+            || self.param_stats.is_some()
+            || self.comparison.is_some()
+            || self.logins.is_some()
+            || self.missing_paths.is_some()
+            || self.clusters.is_some()
+    }
+
+    fn collect_global(&mut self, ip: &str, entry: &LogEntry, params: &[(String, String)]) {
+        if let Some(traffic) = &mut self.traffic
+            && let Some(timestamp) = entry.timestamp
+        {
+            traffic.add(ip, timestamp);
+        }
+        if let Some(global_stats) = &mut self.global_stats {
+            global_stats.collect(ip, entry);
+        }
+        if let Some(param_stats) = &mut self.param_stats {
+            param_stats.collect(ip, params);
+        }
+        if let Some(comparison) = &mut self.comparison {
+            comparison.add(ip, entry);
+        }
+        if let Some(missing_paths) = &mut self.missing_paths {
+            missing_paths.add(ip, entry);
+        }
+        if let Some(clusters) = &mut self.clusters {
+            clusters.add(ip, entry);
+        }
+    }
+
     pub fn process_log(
         &mut self,
         ip_map: &mut HashMap<String, IpInfo>,
@@ -61,8 +184,7 @@ impl LogProcessor {
 
                 ParseType::IpOnly => {
                     if let Some(ip) = LogEntry::parse_ip(&line) {
-                        let entry = ip_map.entry(ip.to_string()).or_insert_with(IpInfo::new);
-                        entry.increment();
+                        self.count_ip(ip_map, ip, &line, None);
                     }
                 }
 
@@ -71,8 +193,7 @@ impl LogProcessor {
 
                         // println!("{timestamp:?}");
 
-                        let entry = ip_map.entry(ip.to_string()).or_insert_with(IpInfo::new);
-                        entry.increment();
+                        self.count_ip(ip_map, ip, &line, Some(timestamp));
                         if timestamp > self.most_recent_timestamp {
                             self.most_recent_timestamp = timestamp;
                             // println!("Most recent timestamp: {timestamp}");
@@ -90,37 +211,71 @@ impl LogProcessor {
                                 return Ok(processed_lines); // Break out of the processing loop
                             }
                         }
-                        entry.timestamps.push(timestamp);
                     }
                 }
 
                 _ => {
 
                     if let Some(ip) = LogEntry::parse_ip(&line) {
-                        if should_filter_ips && !self.filter_ips.contains(ip) {
-                            ip_map.remove(ip);
+                        let key = self.grouping.key(ip);
+                        let tracked = !should_filter_ips || self.filter_ips.contains(key.as_ref());
+                        if !tracked {
+                            ip_map.remove(key.as_ref());
+                        }
 
-                            if let Some(break_line) = &self.break_line{
-                                if break_line == &line {
-                                    // println!("Breakin on line: {processed_lines}");
-                                    return  Ok(processed_lines);
+                        if (tracked || self.collects_all_entries())
+                            && let Some(mut entry) = LogEntry::parse(&line)
+                        {
+                            let params = match (&self.param_stats, &entry.url) {
+                                (Some(_), Some(url)) => parse_query(url),
+                                _ => Vec::new(),
+                            };
+                            let rule_hits: Vec<String> = match &self.rules {
+                                Some(rules) if tracked => rules.matches(&entry).into_iter().map(str::to_string).collect(),
+                                _ => Vec::new(),
+                            };
+                            let disallowed = match (&self.robots, &entry.url) {
+                                (Some(robots), Some(url)) if tracked => {
+                                    let ua = entry.ua.as_deref().unwrap_or_default();
+                                    (!robots.allows(ua, url)).then(|| url.clone())
                                 }
+                                _ => None,
+                            };
+                            // login paths are matched before rewrite rules can change them
+                            if let Some(logins) = &mut self.logins {
+                                logins.add(ip, &entry);
+                            }
+                            if let Some(normalizer) = &self.url_normalizer {
+                                entry.url = entry.url.map(|url| normalizer.normalize(&url));
+                            }
+                            self.collect_global(ip, &entry, &params);
+                            if tracked {
+                                let ip_info = ip_map.entry(key.into_owned()).or_insert_with(IpInfo::new);
+                                if self.sketches.is_some() {
+                                    // the first pass only fed the sketches, count tracked IPs exactly
+                                    Self::count_entry(ip_info, ip, &line, self.grouping.is_grouped());
+                                }
+                                if self.track_hits {
+                                    ip_info.record_hit(ip, &entry);
+                                }
+                                if let Some(session_gap) = self.ua_session_gap {
+                                    ip_info.track_ua(&entry, session_gap);
+                                }
+                                ip_info.collect_params(&params);
+                                ip_info.add_rule_hits(rule_hits);
+                                if let Some(url) = disallowed {
+                                    ip_info.add_disallowed(url);
+                                }
+                                ip_info.collect_entry(entry);
                             }
-
-                            continue;
-                        }
-
-                        if let Some(entry) = LogEntry::parse(&line) {
-                            let ip_info = ip_map.entry(ip.to_string()).or_insert_with(IpInfo::new);
-                            ip_info.collect_entry(entry);
                         }
                     }
 
-                    if let Some(break_line) = &self.break_line{
-                        if break_line == &line {
-                            // println!("Breakin on line: {processed_lines}");
-                            return  Ok(processed_lines);
-                        }
+                    if let Some(break_line) = &self.break_line
+                        && break_line == &line
+                    {
+                        // println!("Breakin on line: {processed_lines}");
+                        return  Ok(processed_lines);
                     }
 
                 }
@@ -0,0 +1,385 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    grouping::{GroupBy, Grouping},
+    log_entry::LogEntry,
+};
+
+/// Failures in a row before a success counts as a success after failures.
+const FAILURE_STREAK: usize = 3;
+/// IPs of one subnet needed to call low volume attempts a distributed attack.
+const DISTRIBUTED_MIN_IPS: usize = 5;
+/// Low volume IPs failing on one endpoint within one window that make credential stuffing.
+const STUFFING_MIN_IPS: usize = 10;
+
+#[derive(Debug, Default)]
+struct Attempts {
+    // (time, succeeded) in log order, sorted before reporting
+    events: Vec<(Option<DateTime<Local>>, bool)>,
+}
+
+/// POST requests to login endpoints of every client.
+#[derive(Debug)]
+pub struct LoginDetector {
+    paths: Vec<String>,
+    subnets: Grouping,
+    ips: HashMap<String, Attempts>,
+    window_secs: i64,
+    /// Failures per IP by login path index and window start
+    windows: HashMap<(usize, i64), HashMap<String, usize>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LoginStats {
+    pub key: String,
+    pub ips: usize,
+    pub attempts: usize,
+    pub failures: usize,
+    pub successes: usize,
+    pub attempts_per_minute: f64,
+    /// Successes that followed a streak of failures
+    pub success_after_failures: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DistributedAttack {
+    pub subnet: String,
+    pub ips: usize,
+    pub failures: usize,
+}
+
+/// Many unrelated low volume IPs failing on one endpoint within one window.
+#[derive(Serialize, Debug, Clone)]
+pub struct CredentialStuffing {
+    pub path: String,
+    pub start: DateTime<Local>,
+    pub ips: usize,
+    pub subnets: usize,
+    pub failures: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LoginReport {
+    pub paths: Vec<String>,
+    pub ips: Vec<LoginStats>,
+    pub subnets: Vec<LoginStats>,
+    /// Subnets where many IPs stay below the failure threshold on their own
+    pub distributed: Vec<DistributedAttack>,
+    /// Endpoints and windows where many low volume IPs fail, regardless of their subnets
+    pub stuffing: Vec<CredentialStuffing>,
+    /// IPs with failures below the threshold, across all subnets
+    pub low_volume_ips: usize,
+    pub low_volume_failures: usize,
+}
+
+impl LoginDetector {
+    pub fn new(paths: &[String], window_minutes: u32) -> Self {
+        Self {
+            paths: paths.to_vec(),
+            subnets: Grouping::new(GroupBy::Slash24, None),
+            ips: HashMap::new(),
+            window_secs: i64::from(window_minutes.max(1)) * 60,
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Index of the login path the request posts to.
+    fn login_path(&self, request: &str) -> Option<usize> {
+        let mut parts = request.split(' ');
+        if parts.next() != Some("POST") {
+            return None;
+        }
+        let target = parts.next().unwrap_or_default();
+        let path = target.split(['?', '#']).next().unwrap_or_default();
+        self.paths
+            .iter()
+            .position(|login| path == login || path.trim_end_matches('/') == login.trim_end_matches('/'))
+    }
+
+    /// 401, 403 and 200 (the form shown again) are failures, redirects are successes.
+    pub fn add(&mut self, ip: &str, entry: &LogEntry) {
+        let (Some(request), Some(status)) = (&entry.url, entry.status) else {
+            return;
+        };
+        let Some(path) = self.login_path(request) else {
+            return;
+        };
+        let succeeded = match status {
+            200 | 401 | 403 => false,
+            301..=303 => true,
+            _ => return,
+        };
+        let attempts = match self.ips.get_mut(ip) {
+            Some(attempts) => attempts,
+            None => self.ips.entry(ip.to_string()).or_default(),
+        };
+        attempts.events.push((entry.timestamp, succeeded));
+        if !succeeded && let Some(timestamp) = entry.timestamp {
+            let window = timestamp.timestamp() - timestamp.timestamp().rem_euclid(self.window_secs);
+            *self
+                .windows
+                .entry((path, window))
+                .or_default()
+                .entry(ip.to_string())
+                .or_insert(0) += 1;
+        }
+    }
+
+    fn stats(key: String, ips: usize, events: &[(Option<DateTime<Local>>, bool)]) -> LoginStats {
+        let successes = events.iter().filter(|(_, succeeded)| *succeeded).count();
+        let times: Vec<DateTime<Local>> = events.iter().filter_map(|(time, _)| *time).collect();
+        let minutes = match (times.iter().min(), times.iter().max()) {
+            (Some(first), Some(last)) => ((*last - *first).num_seconds() as f64 / 60.0).max(1.0),
+            _ => 1.0,
+        };
+
+        let mut streak = 0;
+        let mut success_after_failures = 0;
+        for (_, succeeded) in events {
+            if !succeeded {
+                streak += 1;
+                continue;
+            }
+            if streak >= FAILURE_STREAK {
+                success_after_failures += 1;
+            }
+            streak = 0;
+        }
+
+        LoginStats {
+            key,
+            ips,
+            attempts: events.len(),
+            failures: events.len() - successes,
+            successes,
+            attempts_per_minute: events.len() as f64 / minutes,
+            success_after_failures,
+        }
+    }
+
+    /// IPs and subnets with at least `min_failures` failed logins, most failures first.
+    pub fn report(&mut self, min_failures: usize) -> LoginReport {
+        for attempts in self.ips.values_mut() {
+            attempts.events.sort_by_key(|(time, _)| *time);
+        }
+
+        let mut ips = Vec::new();
+        let mut subnets: HashMap<String, Vec<&str>> = HashMap::new();
+        let mut low_volume_ips = 0;
+        let mut low_volume_failures = 0;
+        for (ip, attempts) in &self.ips {
+            let stats = Self::stats(ip.clone(), 1, &attempts.events);
+            if stats.failures >= min_failures || stats.success_after_failures > 0 {
+                ips.push(stats);
+            } else if stats.failures > 0 {
+                low_volume_ips += 1;
+                low_volume_failures += stats.failures;
+            }
+            subnets
+                .entry(self.subnets.key(ip).into_owned())
+                .or_default()
+                .push(ip);
+        }
+
+        let mut subnet_stats = Vec::new();
+        let mut distributed = Vec::new();
+        for (subnet, members) in subnets {
+            let mut events: Vec<(Option<DateTime<Local>>, bool)> = members
+                .iter()
+                .flat_map(|ip| self.ips[*ip].events.iter().copied())
+                .collect();
+            events.sort_by_key(|(time, _)| *time);
+            let stats = Self::stats(subnet.clone(), members.len(), &events);
+            if stats.failures < min_failures {
+                continue;
+            }
+
+            let quiet_members = members
+                .iter()
+                .filter(|ip| {
+                    let failures = self.ips[**ip].events.iter().filter(|(_, ok)| !ok).count();
+                    failures < min_failures
+                })
+                .count();
+            if quiet_members >= DISTRIBUTED_MIN_IPS {
+                distributed.push(DistributedAttack {
+                    subnet,
+                    ips: quiet_members,
+                    failures: stats.failures,
+                });
+            }
+            subnet_stats.push(stats);
+        }
+
+        let mut stuffing = Vec::new();
+        for ((path, window), failures) in &self.windows {
+            let quiet: Vec<(&String, &usize)> = failures
+                .iter()
+                .filter(|(ip, _)| {
+                    let failures = self.ips[*ip].events.iter().filter(|(_, ok)| !ok).count();
+                    failures < min_failures
+                })
+                .collect();
+            if quiet.len() < STUFFING_MIN_IPS {
+                continue;
+            }
+            let Some(start) = DateTime::from_timestamp(*window, 0) else {
+                continue;
+            };
+            stuffing.push(CredentialStuffing {
+                path: self.paths[*path].clone(),
+                start: start.with_timezone(&Local),
+                ips: quiet.len(),
+                subnets: quiet
+                    .iter()
+                    .map(|(ip, _)| self.subnets.key(ip))
+                    .collect::<HashSet<_>>()
+                    .len(),
+                failures: quiet.iter().map(|(_, failures)| **failures).sum(),
+            });
+        }
+
+        let by_failures = |a: &LoginStats, b: &LoginStats| {
+            b.failures.cmp(&a.failures).then_with(|| a.key.cmp(&b.key))
+        };
+        ips.sort_unstable_by(by_failures);
+        subnet_stats.sort_unstable_by(by_failures);
+        distributed
+            .sort_unstable_by(|a, b| b.ips.cmp(&a.ips).then_with(|| a.subnet.cmp(&b.subnet)));
+        stuffing.sort_unstable_by(|a, b| {
+            b.ips
+                .cmp(&a.ips)
+                .then_with(|| a.start.cmp(&b.start))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        LoginReport {
+            paths: self.paths.clone(),
+            ips,
+            subnets: subnet_stats,
+            distributed,
+            stuffing,
+            low_volume_ips,
+            low_volume_failures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(request: &str, status: u16, minute: i64) -> LogEntry {
+        LogEntry {
+            timestamp: Some(
+                DateTime::from_timestamp(minute * 60, 0)
+                    .unwrap()
+                    .with_timezone(&Local),
+            ),
+            url: Some(request.to_string()),
+            referrer: None,
+            ua: None,
+            status: Some(status),
+            bytes: None,
+            duration_ms: None,
+        }
+    }
+
+    fn detector() -> LoginDetector {
+        LoginDetector::new(&["/login".to_string()], 60)
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        let mut detector = detector();
+        for status in [200, 401, 403, 301, 302, 303, 404, 500] {
+            detector.add("10.0.0.1", &login("POST /login HTTP/1.1", status, 0));
+        }
+        let report = detector.report(1);
+        assert_eq!(report.ips.len(), 1);
+        assert_eq!(
+            (
+                report.ips[0].attempts,
+                report.ips[0].failures,
+                report.ips[0].successes
+            ),
+            (6, 3, 3)
+        );
+    }
+
+    #[test]
+    fn only_posts_to_login_paths_count() {
+        let mut detector = detector();
+        detector.add("10.0.0.1", &login("GET /login HTTP/1.1", 401, 0));
+        detector.add("10.0.0.1", &login("POST /other HTTP/1.1", 401, 0));
+        detector.add("10.0.0.1", &login("POST /login/?next=/ HTTP/1.1", 401, 0));
+        let report = detector.report(1);
+        assert_eq!(report.ips[0].attempts, 1);
+    }
+
+    #[test]
+    fn success_after_a_failure_streak() {
+        let mut detector = detector();
+        for (minute, status) in [(0, 401), (1, 401), (2, 401), (3, 302)] {
+            detector.add("10.0.0.1", &login("POST /login HTTP/1.1", status, minute));
+        }
+        for (minute, status) in [(0, 401), (1, 401), (2, 302)] {
+            detector.add("10.0.0.2", &login("POST /login HTTP/1.1", status, minute));
+        }
+        let report = detector.report(100);
+        assert_eq!(report.ips.len(), 1);
+        assert_eq!(report.ips[0].key, "10.0.0.1");
+        assert_eq!(report.ips[0].success_after_failures, 1);
+        assert_eq!((report.low_volume_ips, report.low_volume_failures), (1, 2));
+    }
+
+    #[test]
+    fn quiet_ips_of_one_subnet_are_distributed() {
+        let mut detector = detector();
+        for host in 1..=DISTRIBUTED_MIN_IPS {
+            for minute in 0..2 {
+                let ip = format!("10.0.0.{host}");
+                detector.add(&ip, &login("POST /login HTTP/1.1", 401, minute));
+            }
+        }
+        let report = detector.report(3);
+        assert!(report.ips.is_empty());
+        assert_eq!(report.subnets[0].key, "10.0.0.0/24");
+        assert_eq!(report.distributed.len(), 1);
+        assert_eq!(
+            (report.distributed[0].ips, report.distributed[0].failures),
+            (DISTRIBUTED_MIN_IPS, DISTRIBUTED_MIN_IPS * 2)
+        );
+    }
+
+    #[test]
+    fn quiet_ips_across_subnets_in_one_window_are_stuffing() {
+        let mut detector = detector();
+        for subnet in 0..STUFFING_MIN_IPS {
+            let ip = format!("10.0.{subnet}.1");
+            detector.add(&ip, &login("POST /login HTTP/1.1", 401, 5));
+        }
+        let report = detector.report(3);
+        assert!(report.distributed.is_empty());
+        assert_eq!(report.stuffing.len(), 1);
+        let attack = &report.stuffing[0];
+        assert_eq!(attack.path, "/login");
+        assert_eq!(
+            (attack.ips, attack.subnets, attack.failures),
+            (STUFFING_MIN_IPS, STUFFING_MIN_IPS, STUFFING_MIN_IPS)
+        );
+    }
+
+    #[test]
+    fn stuffing_needs_one_window() {
+        let mut detector = detector();
+        for subnet in 0..STUFFING_MIN_IPS {
+            let ip = format!("10.0.{subnet}.1");
+            let minute = if subnet % 2 == 0 { 5 } else { 65 };
+            detector.add(&ip, &login("POST /login HTTP/1.1", 401, minute));
+        }
+        assert!(detector.report(3).stuffing.is_empty());
+    }
+}
@@ -1,34 +1,78 @@
-mod cache;
+mod anomaly;
 mod cli_options;
+mod clusters;
+mod crawler_verify;
+mod diff;
 mod file_reader;
 mod free_ip_api;
+mod global_stats;
+mod grouping;
 mod ip_info;
 mod ip_location;
 mod log_entry;
 mod log_processor;
+mod login;
 mod printer;
+mod query_params;
+mod ranking;
+mod regularity;
+mod report;
+mod reputation;
+mod robots;
+mod rules;
+mod scanning;
+mod scoring;
+mod sessions;
+mod sketch;
 mod slack_webhook;
+mod timeline;
+mod traps;
+mod ua_classifier;
+mod ua_rotation;
+mod url_normalizer;
 
+use anomaly::{Thresholds, WindowComparison};
 use clap::Parser;
 use cli_options::CliOptions;
+use clusters::ClusterTracker;
+use crawler_verify::{Crawler, CrawlerVerifier, Verification};
+use diff::{Snapshot, SnapshotDiff};
 use free_ip_api::FreeIpApi;
+use global_stats::GlobalStats;
+use grouping::{AsnDb, GroupBy, Grouping};
 use ip_info::IpInfo;
 use log_processor::{LogProcessor, ParseType};
+use login::LoginDetector;
 use printer::Printer;
+use query_params::{ParamReport, ParamStats};
+use ranking::{SortKey, rank_ips};
+use regularity::TimingStats;
+use report::{GlobalReport, IpReport, Report};
+use reputation::Reputation;
+use robots::{Robots, RobotsFinding};
+use rules::RuleSet;
+use scanning::{MissingPaths, ScanFinding};
+use scoring::{Scorer, Signal, score_ips};
+use sessions::SessionSummary;
+use sketch::{LatencySummary, SketchSummary, Sketches};
 use slack_webhook::{Message, SlackWebhook};
+use timeline::{Timeline, TrafficTimeline};
+use traps::TrapPaths;
+use ua_classifier::UaCategory;
+use ua_rotation::{DEFAULT_SESSION_GAP, UaRotation};
+use url_normalizer::UrlNormalizer;
 
+use chrono::TimeDelta;
 use std::{
     cmp::Reverse,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
+    path::Path,
     time::{Duration, Instant},
 };
 
-fn ip_map_to_vect(ip_map: &HashMap<String, IpInfo>) -> Vec<(&String, &IpInfo)> {
-    let mut ip_vec: Vec<(&String, &IpInfo)> = ip_map.iter().collect();
-    ip_vec.sort_unstable_by_key(|(_, info)| Reverse(info.count));
-    ip_vec
-}
+const MAX_MEMBERS_SHOWN: usize = 10;
+const MAX_FINDINGS_SHOWN: usize = 10;
 
 fn count_hashmap_to_vect(map: &HashMap<String, usize>) -> Vec<(&String, &usize)> {
     let mut vec: Vec<(&String, &usize)> = map.iter().collect();
@@ -36,24 +80,178 @@ fn count_hashmap_to_vect(map: &HashMap<String, usize>) -> Vec<(&String, &usize)>
     vec
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let timer = Instant::now();
-    let opts = CliOptions::parse();
-    let mut ip_map: HashMap<String, IpInfo> = HashMap::new();
-    let mut log_processor = LogProcessor::new(&opts.file_path, opts.filter_hours)?;
+/// The IP standing for a key, the busiest member of a group.
+fn representative_ip(key: &str, ip_info: &IpInfo) -> String {
+    match count_hashmap_to_vect(&ip_info.members).first() {
+        Some((member, _)) => member.to_string(),
+        None => key.to_string(),
+    }
+}
 
-    let line_count = match opts.filter_hours {
-        Some(_filter_hours) => log_processor.process_log(&mut ip_map, ParseType::IpAndTimestamp)?,
-        None => log_processor.process_log(&mut ip_map, ParseType::IpOnly)?,
-    };
+fn scoring_enabled(opts: &CliOptions) -> bool {
+    opts.score || opts.slack_min_score.is_some() || opts.sort_by.contains(&SortKey::Score)
+}
 
-    if opts.max_ips != 0 {
-        log_processor.filter_ips = ip_map_to_vect(&ip_map)
+fn score(ip_map: &mut HashMap<String, IpInfo>, log_processor: &LogProcessor, opts: &CliOptions) {
+    let mut unavailable = Vec::new();
+    if opts.asn_db.is_none() {
+        unavailable.push(Signal::Datacenter);
+    }
+    if log_processor.rules.is_none() {
+        unavailable.push(Signal::RuleHits);
+    }
+    if opts.reputation.is_empty() {
+        unavailable.push(Signal::Reputation);
+    }
+    let scorer = Scorer::new(&opts.score_weight, &unavailable);
+    score_ips(ip_map, &scorer, opts.max_cv, |key, info| {
+        log_processor.grouping.asn(&representative_ip(key, info))
+    });
+}
+
+/// The first `--max-ips` IPs of the ranking, plus every trapped IP.
+fn top_ips(
+    ip_map: &HashMap<String, IpInfo>,
+    opts: &CliOptions,
+    log_processor: &LogProcessor,
+) -> HashSet<String> {
+    let mut ips: HashSet<String> =
+        rank_ips(ip_map, &opts.sort_by, opts.order, log_processor.get_latest_timestamp())
             .into_iter()
             .take(opts.max_ips)
             .map(|(ip, _)| ip.to_string())
             .collect();
+    if let Some(traps) = &log_processor.traps {
+        ips.extend(traps.trapped.keys().cloned());
+    }
+    ips
+}
+
+/// Sets up the log processor with the aggregations enabled by the options.
+fn new_log_processor(path: &Path, opts: &CliOptions) -> Result<LogProcessor, Box<dyn Error>> {
+    let mut log_processor = LogProcessor::new(path, opts.filter_hours)?;
+
+    let asn_db = match &opts.asn_db {
+        Some(path) => Some(AsnDb::load(path)?),
+        None => None,
+    };
+    if opts.group_by == GroupBy::Asn && asn_db.is_none() {
+        return Err("--group-by asn requires --asn-db".into());
+    }
+    log_processor.grouping = Grouping::new(opts.group_by, asn_db);
+
+    if opts.global_timeline {
+        log_processor.traffic = Some(TrafficTimeline::new(opts.timeline_interval));
+    }
+
+    log_processor.track_hits = opts.session_gap.is_some();
+    if opts.ua_rotation || opts.min_uas_per_hour.is_some() || scoring_enabled(opts) {
+        log_processor.ua_session_gap = Some(
+            opts.session_gap
+                .map_or(DEFAULT_SESSION_GAP, |minutes| TimeDelta::minutes(i64::from(minutes))),
+        );
+    }
+
+    let url_normalizer = UrlNormalizer::new(opts.strip_query, opts.collapse_ids, &opts.rewrite)?;
+    if url_normalizer.is_active() {
+        log_processor.url_normalizer = Some(url_normalizer);
+    }
+
+    if opts.approximate {
+        log_processor.sketches = Some(Sketches::new(opts.sketch_capacity));
+    }
+
+    if opts.detect_attacks || !opts.rules.is_empty() {
+        let mut rules = RuleSet::builtin();
+        for path in &opts.rules {
+            rules.extend(RuleSet::load(path)?);
+        }
+        log_processor.rules = Some(RuleSet::new(rules)?);
+    }
+
+    if opts.detect_logins {
+        log_processor.logins = Some(LoginDetector::new(&opts.login_paths, opts.login_window));
+    }
+
+    if opts.missing_paths > 0 {
+        log_processor.missing_paths = Some(MissingPaths::default());
+    }
+
+    if let Some(path) = &opts.robots {
+        log_processor.robots = Some(Robots::load(path)?);
+    }
+
+    if !opts.trap_paths.is_empty() {
+        log_processor.traps = Some(TrapPaths::new(&opts.trap_paths)?);
+    }
+
+    if opts.clusters {
+        log_processor.clusters = Some(ClusterTracker::default());
+    }
+
+    if opts.query_params {
+        log_processor.param_stats = Some(ParamStats::new(opts.fuzz_threshold));
+    }
+
+    if let Some(window_hours) = opts.compare_window {
+        log_processor.comparison = Some(WindowComparison::new(
+            window_hours,
+            opts.baseline,
+            opts.baseline_hours,
+        ));
+    }
+
+    if opts.global_top > 0 {
+        log_processor.global_stats = Some(GlobalStats::default());
+    }
+
+    Ok(log_processor)
+}
+
+struct Analysis {
+    ip_map: HashMap<String, IpInfo>,
+    line_count: usize,
+    sketch_summary: Option<SketchSummary>,
+}
+
+/// Runs both passes over the log and applies the IP selection and filters.
+fn analyze(log_processor: &mut LogProcessor, opts: &CliOptions) -> Result<Analysis, Box<dyn Error>> {
+    // loaded before the passes, so a bad list fails fast
+    let reputation = match opts.reputation.is_empty() {
+        true => None,
+        false => Some(Reputation::load(&opts.reputation)?),
+    };
+    for name in &opts.listed_in {
+        if !opts.reputation.iter().any(|(list, _)| list == name) {
+            return Err(format!("--listed-in {name} is not a --reputation list name").into());
+        }
+    }
+    let mut ip_map: HashMap<String, IpInfo> = HashMap::new();
+    let line_count = match opts.filter_hours {
+        Some(_filter_hours) => log_processor.process_log(&mut ip_map, ParseType::IpAndTimestamp)?,
+        None => log_processor.process_log(&mut ip_map, ParseType::IpOnly)?,
+    };
+
+    // keys known after the first pass can limit the full pass to the top IPs,
+    // the others need every IP collected before selecting. In approximate mode
+    // the full pass recounts all IPs kept by the sketch exactly.
+    let select_after_full_pass =
+        opts.approximate || opts.sort_by.iter().any(SortKey::needs_full_pass);
+    let sketch_summary = match &mut log_processor.sketches {
+        Some(sketches) => {
+            log_processor.filter_ips = sketches
+                .top_ips
+                .top(opts.sketch_capacity)
+                .into_iter()
+                .map(|(ip, _, _)| ip.to_string())
+                .chain(log_processor.traps.iter().flat_map(|traps| traps.trapped.keys().cloned()))
+                .collect();
+            Some(sketches.summary())
+        }
+        None => None,
+    };
+    if opts.max_ips != 0 && !select_after_full_pass {
+        log_processor.filter_ips = top_ips(&ip_map, opts, log_processor);
     }
 
     match opts.filter_hours {
@@ -66,18 +264,97 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    if let Some(traps) = &log_processor.traps {
+        for (key, hit) in &traps.trapped {
+            if let Some(ip_info) = ip_map.get_mut(key) {
+                ip_info.trap = Some(hit.clone());
+            }
+        }
+    }
+
+    if let Some(reputation) = &reputation {
+        for (key, ip_info) in ip_map.iter_mut() {
+            let mut tags = Vec::new();
+            let members: Vec<&String> = match ip_info.members.is_empty() {
+                true => vec![key],
+                false => ip_info.members.keys().collect(),
+            };
+            for member in members {
+                for tag in reputation.tags(member) {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+            }
+            ip_info.reputation = tags;
+        }
+    }
+
+    if scoring_enabled(opts) {
+        score(&mut ip_map, log_processor, opts);
+    }
+
+    if opts.max_ips != 0 && select_after_full_pass {
+        log_processor.filter_ips = top_ips(&ip_map, opts, log_processor);
+        ip_map.retain(|ip, _| log_processor.filter_ips.contains(ip));
+    }
+
     // Filter RPM | Requests
-    if opts.filter_rpm.is_some() || opts.filter_requests.is_some() {
+    let filter_clients = !opts.ua_category.is_empty() || !opts.exclude_ua_category.is_empty();
+    if opts.filter_rpm.is_some()
+        || opts.filter_requests.is_some()
+        || opts.regular_only
+        || opts.min_uas_per_hour.is_some()
+        || !opts.listed_in.is_empty()
+        || filter_clients
+    {
         for (ip, ip_info) in ip_map.clone() {
-            if let Some(min_rpm) = opts.filter_rpm {
-                if ip_info.average_rpm() < min_rpm as f64 {
-                    ip_map.remove(&ip);
-                    continue;
-                }
+            // trapped IPs are always shown
+            if ip_info.trap.is_some() {
+                continue;
+            }
+
+            if let Some(min_rpm) = opts.filter_rpm
+                && ip_info.average_rpm() < min_rpm as f64
+            {
+                ip_map.remove(&ip);
+                continue;
+            }
+
+            if let Some(min_requests) = opts.filter_requests
+                && ip_info.count < min_requests
+            {
+                ip_map.remove(&ip);
+                continue;
+            }
+
+            if opts.regular_only
+                && !TimingStats::new(&ip_info.timestamps, opts.max_cv).is_some_and(|timing| timing.regular)
+            {
+                ip_map.remove(&ip);
+                continue;
             }
 
-            if let Some(min_requests) = opts.filter_requests {
-                if ip_info.count < min_requests {
+            if let Some(min_uas) = opts.min_uas_per_hour
+                && ip_info.ua_history.max_uas_per_hour() < min_uas
+            {
+                ip_map.remove(&ip);
+                continue;
+            }
+
+            if !opts.listed_in.is_empty()
+                && !ip_info.reputation.iter().any(|tag| opts.listed_in.contains(tag))
+            {
+                ip_map.remove(&ip);
+                continue;
+            }
+
+            if filter_clients {
+                let category = ua_classifier::dominant(&ip_info.ua_map)
+                    .map_or(UaCategory::Empty, |(class, _)| class.category);
+                if (!opts.ua_category.is_empty() && !opts.ua_category.contains(&category))
+                    || opts.exclude_ua_category.contains(&category)
+                {
                     ip_map.remove(&ip);
                     continue;
                 }
@@ -85,37 +362,235 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    Ok(Analysis {
+        ip_map,
+        line_count,
+        sketch_summary,
+    })
+}
+
+/// Loads a saved JSON report, or analyzes a log the same way as a normal run but
+/// without cutting it to --max-ips or --global-top, so a rank change is no removal.
+fn snapshot(path: &Path, opts: &CliOptions) -> Result<Snapshot, Box<dyn Error>> {
+    if path.extension().is_some_and(|extension| extension == "json") {
+        return Snapshot::load(path);
+    }
+    let opts = CliOptions {
+        max_ips: 0,
+        ..opts.clone()
+    };
+    let mut log_processor = new_log_processor(path, &opts)?;
+    let ip_map = analyze(&mut log_processor, &opts)?.ip_map;
+    let global = log_processor
+        .global_stats
+        .as_ref()
+        .map(|stats| GlobalReport::new(stats, usize::MAX, opts.sort_by[0]));
+    Ok(Snapshot::new(&ip_map, global.as_ref()))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let timer = Instant::now();
+    let opts = CliOptions::parse();
+    if let Some(old_path) = &opts.diff_against {
+        let old = snapshot(old_path, &opts)?;
+        // collect the global dimensions of a saved report for the current log as well
+        let new_opts = match old.global.is_some() && opts.global_top == 0 && !opts.approximate {
+            true => CliOptions {
+                global_top: 1,
+                ..opts.clone()
+            },
+            false => opts.clone(),
+        };
+        let new = snapshot(&opts.file_path, &new_opts)?;
+        let diff = SnapshotDiff::new(&old, &new);
+        if opts.json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else {
+            println!("{}", Printer::new(opts.colors).diff(&diff, opts.max_ips, opts.global_top));
+        }
+        return Ok(());
+    }
+
+    let mut log_processor = new_log_processor(&opts.file_path, &opts)?;
+    let Analysis {
+        mut ip_map,
+        line_count,
+        sketch_summary,
+    } = analyze(&mut log_processor, &opts)?;
+
     let elapsed = timer.elapsed();
 
     let mut time_fetching = Duration::default();
     if opts.geolocate && !ip_map.is_empty() {
-        let ip_set = ip_map.keys().cloned().collect();
+        // groups are located by their busiest member
+        let located_ips: HashMap<String, String> = ip_map
+            .iter()
+            .map(|(key, info)| (representative_ip(key, info), key.clone()))
+            .collect();
+        let ip_set = located_ips.keys().cloned().collect();
         for loc in FreeIpApi::get_loc_info(ip_set).await? {
-            if let Some(ip) = loc.ip_address.clone() {
-                if ip_map.contains_key(&ip) {
-                    ip_map
-                        .entry(ip)
-                        .and_modify(|data| data.location_data = Some(loc));
-                }
+            if let Some(ip) = loc.ip_address.clone()
+                && let Some(key) = located_ips.get(&ip)
+            {
+                ip_map
+                    .entry(key.clone())
+                    .and_modify(|data| data.location_data = Some(loc));
             }
         }
         time_fetching = timer.elapsed();
     }
 
-    let ip_vec = ip_map_to_vect(&ip_map);
+    if opts.verify_crawlers {
+        let claims = ip_map
+            .iter()
+            .filter_map(|(key, info)| {
+                let (class, _) = ua_classifier::dominant(&info.ua_map)?;
+                let crawler = Crawler::from_family(&class.family)?;
+                Some((key.clone(), representative_ip(key, info), crawler))
+            })
+            .collect();
+        let verifier = CrawlerVerifier::new(opts.dns_server)?;
+        for (key, check) in verifier.verify_all(claims).await? {
+            if let Some(ip_info) = ip_map.get_mut(&key) {
+                ip_info.crawler_check = Some(check);
+            }
+        }
+        if opts.hide_verified_crawlers {
+            // a verified crawler in a trap still breaks the rules, keep it
+            ip_map.retain(|_, info| {
+                info.trap.is_some()
+                    || !info
+                        .crawler_check
+                        .as_ref()
+                        .is_some_and(|check| check.status == Verification::Verified)
+            });
+        }
+        if scoring_enabled(&opts) {
+            // verified and spoofed crawlers change the user agent signal
+            score(&mut ip_map, &log_processor, &opts);
+        }
+    }
+
+    let ip_vec = rank_ips(
+        &ip_map,
+        &opts.sort_by,
+        opts.order,
+        log_processor.get_latest_timestamp(),
+    );
+    let window = Timeline::window(ip_map.values().map(|info| &info.timestamps));
+    let ip_timeline = |ip_info: &IpInfo| {
+        window.map(|(start, end)| {
+            Timeline::from_timestamps(&ip_info.timestamps, start, end, opts.timeline_interval)
+        })
+    };
 
     let mut output_buff = String::new();
+    let mut slack_buff = String::new();
+    let mut report = Report {
+        processed_lines: line_count,
+        ips: Vec::new(),
+        traffic: None,
+        global: None,
+        approximation: None,
+        params: None,
+        anomalies: None,
+        logins: None,
+        missing_paths: None,
+        clusters: None,
+    };
 
     let printer = Printer::new(opts.colors);
     let mut ln = 0;
     for (ip, ip_info) in ip_vec.clone() {
         ln += 1;
-        output_buff += &printer.ip(ln, ip, ip_info, log_processor.get_latest_timestamp());
-        if opts.geolocate {
-            if let Some(loc) = &ip_info.location_data {
-                output_buff += &printer.location(loc.clone());
-                output_buff += "\n";
-            }
+        let ip_start = output_buff.len();
+        let timeline = ip_timeline(ip_info);
+        let timing = TimingStats::new(&ip_info.timestamps, opts.max_cv);
+        let ua_rotation = match opts.ua_rotation || opts.min_uas_per_hour.is_some() {
+            true => UaRotation::new(&ip_info.ua_history, ip_info.ua_map.len()),
+            false => None,
+        };
+        output_buff += &printer.ip(
+            ln,
+            ip,
+            ip_info,
+            log_processor.get_latest_timestamp(),
+            timeline.as_ref(),
+            timing.as_ref(),
+            ua_rotation.as_ref(),
+        );
+        if opts.geolocate
+            && let Some(loc) = &ip_info.location_data
+        {
+            output_buff += &printer.location(loc.clone());
+            output_buff += "\n";
+        }
+        if let Some(trap) = &ip_info.trap {
+            output_buff += &printer.trap(trap);
+        }
+        if !ip_info.reputation.is_empty() {
+            output_buff += &printer.reputation(&ip_info.reputation);
+        }
+        let client = ua_classifier::dominant(&ip_info.ua_map);
+        if let Some((class, share)) = &client {
+            output_buff += &printer.client(class, *share, ip_info.crawler_check.as_ref());
+        }
+        if let Some(score) = &ip_info.score {
+            output_buff += &printer.score(score);
+        }
+        if let Some(latency) = LatencySummary::new(&ip_info.latency) {
+            output_buff += &printer.latency(&latency);
+        }
+        let scan = match opts.detect_scans {
+            true => ScanFinding::new(
+                ip_info,
+                opts.scan_min_ratio,
+                opts.scan_min_paths,
+                MAX_FINDINGS_SHOWN.max(opts.top_params),
+            ),
+            false => None,
+        };
+        if let Some(scan) = &scan {
+            output_buff += &printer.scan(scan);
+        }
+        let robots_finding = log_processor.robots.as_ref().and_then(|robots| {
+            RobotsFinding::new(ip_info, robots, MAX_FINDINGS_SHOWN.max(opts.top_params))
+        });
+        if let Some(finding) = &robots_finding {
+            output_buff += &printer.robots(finding);
+        }
+        if let Some(rules) = &log_processor.rules
+            && !ip_info.rule_hits.is_empty()
+        {
+            output_buff += &printer.rule_hits(count_hashmap_to_vect(&ip_info.rule_hits), rules);
+        }
+        if !ip_info.members.is_empty() {
+            output_buff += "\n";
+            output_buff += &printer.list(
+                count_hashmap_to_vect(&ip_info.members),
+                "Member IP",
+                MAX_MEMBERS_SHOWN.max(opts.top_params),
+            );
+        }
+        let sessions = opts.session_gap.map(|gap| {
+            SessionSummary::new(
+                &ip_info.hits,
+                TimeDelta::minutes(i64::from(gap)),
+                opts.top_params,
+            )
+        });
+        if let Some(sessions) = &sessions {
+            output_buff += "\n";
+            output_buff += &printer.sessions(sessions, opts.top_params);
+        }
+        let params = match &log_processor.param_stats {
+            Some(_) => ParamReport::for_ip(&ip_info.params, opts.top_params, opts.fuzz_threshold, ip),
+            None => Vec::new(),
+        };
+        if !params.is_empty() {
+            output_buff += "\n";
+            output_buff += &printer.params(&params, ip_info.params.len(), false);
         }
         if opts.top_params > 0 {
             output_buff += "\n";
@@ -138,31 +613,155 @@ async fn main() -> Result<(), Box<dyn Error>> {
             );
             output_buff += "\n";
         }
+        if opts.json {
+            let mut ip_report = IpReport::new(
+                ip,
+                ip_info,
+                log_processor.get_latest_timestamp(),
+                timeline,
+                opts.top_params,
+            );
+            ip_report.sessions = sessions;
+            ip_report.params = params;
+            ip_report.timing = timing;
+            ip_report.ua_rotation = ua_rotation;
+            ip_report.scan = scan;
+            ip_report.robots = robots_finding;
+            if let Some((class, share)) = client {
+                ip_report.client = Some(class);
+                ip_report.client_share = Some(share);
+            }
+            report.ips.push(ip_report);
+        }
+        if let Some(min_score) = opts.slack_min_score
+            && ip_info.score.as_ref().is_some_and(|score| score.total >= min_score)
+        {
+            slack_buff += &output_buff[ip_start..];
+        }
+    }
+
+    if let Some(traffic) = &log_processor.traffic {
+        let rows = traffic.rows(opts.top_params);
+        output_buff += &printer.traffic(&rows, traffic.interval_minutes());
+        if opts.json {
+            report.traffic = Some(rows);
+        }
+    }
+
+    if let Some(global_stats) = &log_processor.global_stats {
+        output_buff += &printer.global_list(
+            GlobalStats::top(&global_stats.urls, opts.global_top, opts.sort_by[0]),
+            "URL",
+            global_stats.urls.len(),
+            Some(&global_stats.url_latency),
+        );
+        output_buff += &printer.global_list(
+            GlobalStats::top(&global_stats.uas, opts.global_top, opts.sort_by[0]),
+            "UA",
+            global_stats.uas.len(),
+            None,
+        );
+        output_buff += &printer.global_list(
+            GlobalStats::top(&global_stats.referrer_domains, opts.global_top, opts.sort_by[0]),
+            "Referring domain",
+            global_stats.referrer_domains.len(),
+            None,
+        );
+        if opts.json {
+            report.global = Some(GlobalReport::new(global_stats, opts.global_top, opts.sort_by[0]));
+        }
     }
 
-    if opts.footer {
+    if let Some(param_stats) = &log_processor.param_stats {
+        let limit = opts.global_top.max(opts.top_params);
+        let params = ParamReport::global(param_stats, limit);
+        if !params.is_empty() {
+            output_buff += &printer.params(&params, param_stats.params.len(), true);
+        }
+        if opts.json {
+            report.params = Some(params);
+        }
+    }
+
+    if let Some(comparison) = &log_processor.comparison
+        && let (Some(current), Some(baseline)) =
+            (comparison.current_window(), comparison.baseline_window())
+    {
+        let anomalies = comparison.anomalies(&Thresholds {
+            growth: opts.growth_threshold,
+            z_score: opts.z_threshold,
+            min_requests: opts.anomaly_min_requests,
+        });
+        output_buff += &printer.anomalies(
+            &anomalies,
+            current,
+            baseline,
+            MAX_FINDINGS_SHOWN.max(opts.top_params),
+        );
+        if opts.json {
+            report.anomalies = Some(anomalies);
+        }
+    }
+
+    if let Some(missing_paths) = &log_processor.missing_paths {
+        let paths = missing_paths.top(opts.missing_paths, opts.top_params.max(1));
+        output_buff += &printer.missing_paths(&paths, missing_paths.paths.len());
+        if opts.json {
+            report.missing_paths = Some(paths);
+        }
+    }
+
+    if let Some(clusters) = &log_processor.clusters {
+        let clusters = clusters.clusters(opts.cluster_min_size, &log_processor.grouping);
+        output_buff += &printer.clusters(&clusters, MAX_FINDINGS_SHOWN.max(opts.top_params));
+        if opts.json {
+            report.clusters = Some(clusters);
+        }
+    }
+
+    if let Some(logins) = &mut log_processor.logins {
+        let login_report = logins.report(opts.login_min_failures);
+        output_buff += &printer.logins(&login_report, MAX_FINDINGS_SHOWN.max(opts.top_params));
+        if opts.json {
+            report.logins = Some(login_report);
+        }
+    }
+
+    if opts.json {
+        report.approximation = sketch_summary.clone();
+    }
+
+    if opts.footer && !opts.json {
         output_buff += &printer.footer(
             line_count,
             elapsed.as_millis(),
             time_fetching.as_millis(),
             opts.geolocate,
+            sketch_summary.as_ref(),
         );
     }
 
-    if !ip_vec.is_empty() && opts.slack {
+    if opts.slack_min_score.is_none() {
+        slack_buff = output_buff.clone();
+    }
+    if !ip_vec.is_empty() && !slack_buff.is_empty() && opts.slack {
         dotenv::dotenv()?;
         if let Ok(webhook_url) = dotenv::var("WEBHOOK") {
             let slack_webhook = SlackWebhook::new(webhook_url);
             let mut text = String::new();
             text += "SUSPICIOUS ACTIVITY\n----------------------------\n";
-            text += &output_buff.clone();
+            text += &slack_buff;
             let msg = Message::new(&text);
             // let msg = Message::new("asd");
             slack_webhook.send_message(msg).await?;
         }
     }
 
-    println!("{output_buff}");
+    if opts.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{output_buff}");
+    }
 
     Ok(())
 }
@@ -1,9 +1,35 @@
 use core::str;
+use std::collections::HashMap;
 
 use ansi_term::Colour;
 use chrono::{DateTime, Local};
+use clap::ValueEnum;
 
-use crate::{ip_info::IpInfo, ip_location::IpLocation};
+use crate::{
+    anomaly::{Anomaly, Dimension},
+    clusters::Cluster,
+    crawler_verify::{CrawlerCheck, Verification},
+    diff::{Change, DiffEntry, SnapshotDiff},
+    global_stats::DimensionStat,
+    ip_info::IpInfo,
+    ip_location::IpLocation,
+    login::{LoginReport, LoginStats},
+    query_params::ParamReport,
+    regularity::TimingStats,
+    robots::RobotsFinding,
+    rules::RuleSet,
+    scanning::{MissingPathReport, ScanFinding},
+    scoring::Score,
+    sessions::SessionSummary,
+    sketch::{LatencySummary, SketchSummary, TDigest},
+    timeline::{Timeline, TrafficRow},
+    traps::TrapHit,
+    ua_classifier::UaClass,
+    ua_rotation::UaRotation,
+};
+
+/// Most characters of a sparkline, longer timelines merge neighbouring buckets.
+const MAX_SPARKLINE_WIDTH: usize = 60;
 
 pub struct Printer {
     colors: bool,
@@ -30,6 +56,7 @@ impl Printer {
         elapsed: u128,
         time_fetching: u128,
         geolocate: bool,
+        sketch_summary: Option<&SketchSummary>,
     ) -> String {
         let pb = Colour::Purple;
         let mut buff = String::new();
@@ -57,21 +84,60 @@ impl Printer {
                 self.opt_color(&format!("{}{}", time_fetching, "ms"), &pb, true)
             );
         }
+        if let Some(summary) = sketch_summary {
+            let percentile = |value: Option<f64>| match value {
+                Some(value) => format!("{value:.1}"),
+                None => "-".to_string(),
+            };
+            buff += &format!(
+                "Approximate mode: {} IPs tracked, counts of untracked IPs are at most {}\n",
+                self.opt_color(&summary.tracked_ips.to_string(), &pb, true),
+                self.opt_color(&summary.count_error_bound.to_string(), &pb, true),
+            );
+            buff += &format!(
+                "~{} distinct IPs, ~{} distinct URLs (±{:.1}%)\n",
+                self.opt_color(&summary.distinct_ips.to_string(), &pb, true),
+                self.opt_color(&summary.distinct_urls.to_string(), &pb, true),
+                summary.distinct_relative_error * 100.0,
+            );
+            buff += &format!(
+                "Requests per minute p50/p95/p99: {}/{}/{}\n",
+                percentile(summary.rpm_p50),
+                percentile(summary.rpm_p95),
+                percentile(summary.rpm_p99),
+            );
+        }
         buff += "\n";
         buff
     }
 
-    pub fn ip(&self, ln: usize, ip: &str, ip_info: &IpInfo, latest_timestamp: DateTime<Local>) -> String {
+    #[allow(clippy::too_many_arguments)]
+    pub fn ip(
+        &self,
+        ln: usize,
+        ip: &str,
+        ip_info: &IpInfo,
+        latest_timestamp: DateTime<Local>,
+        timeline: Option<&Timeline>,
+        timing: Option<&TimingStats>,
+        ua_rotation: Option<&UaRotation>,
+    ) -> String {
         let color = Colour::Cyan;
         let last_access = match ip_info.last_timestamp() {
             Some(timestamp) => timestamp.to_string(),
             None => "Unknown".to_string(),
         };
-        format!(
-            "[{}] {}: ({} requests, average RPM: {}, RPM in last hour: {}, last access: {})\n",
+        let sparkline = match timeline {
+            Some(timeline) => format!(" {}", self.opt_color(&Self::sparkline(&timeline.buckets), &Colour::Green, false)),
+            None => String::new(),
+        };
+        let mut buff = format!(
+            "[{}] {}: ({} requests, {} sent (max {}), average RPM: {}, RPM in last hour: {}, last access: {}){}\n",
             ln,
             self.opt_color(ip, &color, true),
             self.opt_color(&ip_info.count.to_string(), &color, true),
+            self.opt_color(&Self::human_bytes(ip_info.total_bytes), &color, true),
+            self.opt_color(&Self::human_bytes(ip_info.max_bytes), &color, true),
             self.opt_color(&ip_info.average_rpm().round().to_string(), &color, true),
             self.opt_color(
                 &ip_info.average_rpm_last_hour(latest_timestamp).round().to_string(),
@@ -79,9 +145,267 @@ impl Printer {
                 true
             ),
             self.opt_color(&last_access, &color, true),
+            sparkline,
+        );
+        if let Some(timing) = timing {
+            buff += &format!(
+                "\t\tTiming: gap CV {}, gap entropy {:.2} bits, period {} ({:.0}% of {} gaps){}\n",
+                self.opt_color(&format!("{:.2}", timing.cv), &color, true),
+                timing.gap_entropy,
+                self.opt_color(&Self::human_duration(timing.period_secs), &color, true),
+                timing.period_share * 100.0,
+                timing.intervals,
+                if timing.regular {
+                    format!(" {}", self.opt_color("REGULAR", &Colour::Red, true))
+                } else {
+                    String::new()
+                },
+            );
+        }
+        if let Some(rotation) = ua_rotation {
+            buff += &format!(
+                "\t\tUA rotation: {} distinct, up to {} per hour ({:.1} on average), {} switches in {} sessions (max {} per session)\n",
+                self.opt_color(&rotation.distinct_uas.to_string(), &color, true),
+                self.opt_color(&rotation.max_uas_per_hour.to_string(), &color, true),
+                rotation.average_uas_per_hour,
+                self.opt_color(&rotation.switches.to_string(), &color, true),
+                rotation.sessions,
+                rotation.max_switches_per_session,
+            );
+        }
+        buff
+    }
+
+    pub fn client(&self, class: &UaClass, share: f64, check: Option<&CrawlerCheck>) -> String {
+        let color = Colour::Cyan;
+        let mut details = class.family.clone();
+        if let Some(os) = class.os {
+            details += &format!(" on {os}");
+        }
+        if let Some(device) = class.device {
+            details += &format!(" ({device:?})").to_lowercase();
+        }
+        let verification = match check {
+            Some(check) => {
+                let (status, status_color) = match check.status {
+                    Verification::Verified => ("verified", Colour::Green),
+                    Verification::Spoofed => ("SPOOFED", Colour::Red),
+                    Verification::Unresolved => ("unresolved", Colour::Yellow),
+                };
+                format!(
+                    ", {} {:?} crawler{}",
+                    self.opt_color(status, &status_color, true),
+                    check.crawler,
+                    match &check.hostname {
+                        Some(hostname) => format!(" ({hostname})"),
+                        None => String::new(),
+                    }
+                )
+            }
+            None => String::new(),
+        };
+        format!(
+            "\t\tClient: {} {} ({:.0}% of requests){}\n",
+            self.opt_color(&class.category.to_string(), &color, true),
+            details,
+            share * 100.0,
+            verification,
+        )
+    }
+
+    pub fn score(&self, score: &Score) -> String {
+        let color = match score.total {
+            total if total >= 50.0 => Colour::Red,
+            total if total >= 25.0 => Colour::Yellow,
+            _ => Colour::Green,
+        };
+        let mut signals: Vec<_> = score.signals.iter().filter(|signal| signal.points > 0.0).collect();
+        signals.sort_unstable_by(|a, b| b.points.total_cmp(&a.points));
+        let explanation = signals
+            .iter()
+            .map(|signal| {
+                let name = signal.signal.to_possible_value().map(|name| name.get_name().to_string());
+                format!(
+                    "{} +{:.1} ({:.2})",
+                    name.unwrap_or_default(),
+                    signal.points,
+                    signal.value
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "\t\tSuspicion score: {}/100 {}\n",
+            self.opt_color(&format!("{:.1}", score.total), &color, true),
+            if explanation.is_empty() { String::new() } else { format!("({explanation})") },
         )
     }
 
+    pub fn scan(&self, scan: &ScanFinding) -> String {
+        let color = Colour::Red;
+        let mut buff = format!(
+            "\t{} ({} of requests are 404, {} distinct missing paths)\n",
+            self.opt_color("404 SCANNING", &color, true),
+            self.opt_color(&format!("{:.0}%", scan.not_found_ratio * 100.0), &color, true),
+            self.opt_color(&scan.missing_paths.to_string(), &color, true),
+        );
+        for (path, count) in &scan.top_paths {
+            buff += &format!(
+                "\t\t{} ({})\n",
+                self.opt_color(path, &color, false),
+                self.opt_color(&format!("{count}x"), &color, true),
+            );
+        }
+        buff
+    }
+
+    pub fn robots(&self, finding: &RobotsFinding) -> String {
+        let color = Colour::Red;
+        let rate = match finding.permitted_rpm {
+            Some(permitted) => format!(
+                ", {} requests/min against {:.1} permitted by Crawl-delay {}s{}",
+                self.opt_color(&format!("{:.1}", finding.observed_rpm), &color, true),
+                permitted,
+                finding.crawl_delay.unwrap_or_default(),
+                if finding.too_fast {
+                    format!(" {}", self.opt_color("TOO FAST", &color, true))
+                } else {
+                    String::new()
+                },
+            ),
+            None => String::new(),
+        };
+        let mut buff = format!(
+            "\t{} (user-agent: {}, {} disallowed requests{})\n",
+            self.opt_color("ROBOTS.TXT VIOLATION", &color, true),
+            finding.agent,
+            self.opt_color(&finding.disallowed_requests.to_string(), &color, true),
+            rate,
+        );
+        for (url, count) in &finding.disallowed_urls {
+            buff += &format!(
+                "\t\t{} ({})\n",
+                self.opt_color(url, &color, false),
+                self.opt_color(&format!("{count}x"), &color, true),
+            );
+        }
+        buff
+    }
+
+    pub fn missing_paths(&self, paths: &[MissingPathReport], unique: usize) -> String {
+        let color = Colour::Yellow;
+        let mut buff = format!(
+            "{} Most requested {}: ({} unique)\n",
+            paths.len(),
+            self.opt_color("missing paths", &color, true),
+            self.opt_color(&unique.to_string(), &color, true),
+        );
+        for path in paths {
+            buff += &format!(
+                "\t{} ({}, {} IPs)\n",
+                self.opt_color(&path.url, &color, false),
+                self.opt_color(&format!("{}x", path.count), &color, true),
+                path.ips,
+            );
+            for (referrer, count) in &path.referrers {
+                buff += &format!("\t\tlinked from {referrer} ({count}x)\n");
+            }
+        }
+        buff += "\n";
+        buff
+    }
+
+    pub fn trap(&self, hit: &TrapHit) -> String {
+        let color = Colour::Red;
+        let time = match hit.timestamp {
+            Some(timestamp) => timestamp.to_string(),
+            None => "Unknown".to_string(),
+        };
+        format!(
+            "\t{} {} at {} ({} trap requests)\n",
+            self.opt_color("TRAPPED", &color, true),
+            self.opt_color(&hit.path, &color, false),
+            self.opt_color(&time, &color, true),
+            hit.hits,
+        )
+    }
+
+    pub fn reputation(&self, lists: &[String]) -> String {
+        format!(
+            "\tListed in: {}\n",
+            self.opt_color(&lists.join(", "), &Colour::Red, true)
+        )
+    }
+
+    pub fn rule_hits(&self, hits: Vec<(&String, &usize)>, rules: &RuleSet) -> String {
+        let color = Colour::Red;
+        let mut buff = format!(
+            "\t{}: ({} rules matched)\n",
+            self.opt_color("Attack signatures", &color, true),
+            self.opt_color(&hits.len().to_string(), &color, true),
+        );
+        for (id, count) in hits {
+            buff += &format!(
+                "\t\t{} ({}) {}\n",
+                self.opt_color(id, &color, false),
+                self.opt_color(&format!("{count}x"), &color, true),
+                rules.description(id).unwrap_or_default(),
+            );
+        }
+        buff
+    }
+
+    pub fn latency(&self, summary: &LatencySummary) -> String {
+        let color = Colour::Yellow;
+        format!(
+            "\t\tLatency p50/p95/p99: {}/{}/{}, server time: {} ({} timed requests)\n",
+            self.opt_color(&Self::human_ms(summary.p50_ms), &color, true),
+            self.opt_color(&Self::human_ms(summary.p95_ms), &color, true),
+            self.opt_color(&Self::human_ms(summary.p99_ms), &color, true),
+            self.opt_color(&Self::human_ms(summary.total_ms), &color, true),
+            summary.requests,
+        )
+    }
+
+    pub fn human_ms(ms: f64) -> String {
+        match ms {
+            ms if ms < 1000.0 => format!("{ms:.1}ms"),
+            ms if ms < 60_000.0 => format!("{:.2}s", ms / 1000.0),
+            ms => Self::human_duration((ms / 1000.0) as i64),
+        }
+    }
+
+    pub fn human_bytes(bytes: u64) -> String {
+        const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = bytes as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => format!("{} {}", bytes, UNITS[0]),
+            _ => format!("{:.1} {}", size, UNITS[unit]),
+        }
+    }
+
+    pub fn sparkline(buckets: &[usize]) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let merged: Vec<usize> = buckets
+            .chunks(buckets.len().div_ceil(MAX_SPARKLINE_WIDTH).max(1))
+            .map(|chunk| chunk.iter().sum())
+            .collect();
+        let buckets = &merged;
+        let max = buckets.iter().copied().max().unwrap_or(0);
+        buckets
+            .iter()
+            .map(|&count| match count {
+                0 => ' ',
+                _ => BARS[count * (BARS.len() - 1) / max],
+            })
+            .collect()
+    }
+
     pub fn list(&self, vec: Vec<(&String, &usize)>, title: &str, limit: usize) -> String{
         let color = Colour::Blue;
         let limited_vec = vec.iter().take(limit);
@@ -105,6 +429,402 @@ impl Printer {
         buff
     }
 
+    pub fn global_list(
+        &self,
+        vec: Vec<(&String, &DimensionStat)>,
+        title: &str,
+        unique: usize,
+        latency: Option<&HashMap<String, TDigest>>,
+    ) -> String {
+        let color = Colour::Green;
+        let mut buff = String::new();
+
+        buff += &format!(
+            "{} Most common {}s overall: ({} unique)\n",
+            vec.len(),
+            self.opt_color(title, &color, true),
+            self.opt_color(&unique.to_string(), &color, true)
+        );
+        for (string, stat) in vec {
+            buff += &format!(
+                "\t{} ({}, {} IPs, {} sent, max {})\n",
+                self.opt_color(string, &color, false),
+                self.opt_color(&format!("{}x", stat.count), &color, true),
+                self.opt_color(&stat.ips.len().to_string(), &color, true),
+                self.opt_color(&Self::human_bytes(stat.bytes), &color, true),
+                self.opt_color(&Self::human_bytes(stat.max_bytes), &color, true),
+            );
+            if let Some(summary) = latency
+                .and_then(|latency| latency.get(string))
+                .and_then(LatencySummary::new)
+            {
+                buff += &self.latency(&summary);
+            }
+        }
+        buff += "\n";
+        buff
+    }
+
+    pub fn traffic(&self, rows: &[TrafficRow], interval_minutes: u32) -> String {
+        const BAR_WIDTH: usize = 40;
+        let color = Colour::Yellow;
+        let max = rows.iter().map(|row| row.count).max().unwrap_or(0).max(1);
+        let mut buff = String::new();
+
+        buff += &format!(
+            "{} (per {} minutes):\n",
+            self.opt_color("Requests over time", &color, true),
+            interval_minutes
+        );
+        for row in rows {
+            let filled = row.count * BAR_WIDTH / max;
+            let bar = "█".repeat(filled) + &" ".repeat(BAR_WIDTH - filled);
+            let top_ips = row
+                .top_ips
+                .iter()
+                .map(|(ip, count)| format!("{ip} ({count}x)"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            buff += &format!(
+                "\t{} {} {:>7} {}{}\n",
+                row.start.format("%Y-%m-%d %H:%M"),
+                self.opt_color(&bar, &color, false),
+                row.count,
+                if row.peak {
+                    self.opt_color("▲ peak ", &Colour::Red, true)
+                } else {
+                    String::new()
+                },
+                top_ips,
+            );
+        }
+        buff += "\n";
+        buff
+    }
+
+    pub fn sessions(&self, summary: &SessionSummary, limit: usize) -> String {
+        let color = Colour::Purple;
+        let mut buff = format!(
+            "\t{}: {} (average duration: {}, longest: {}, average pages: {:.1})\n",
+            self.opt_color("Sessions", &color, true),
+            self.opt_color(&summary.sessions.to_string(), &color, true),
+            self.opt_color(
+                &Self::human_duration(summary.average_duration_secs as i64),
+                &color,
+                true
+            ),
+            self.opt_color(&Self::human_duration(summary.max_duration_secs), &color, true),
+            summary.average_pages,
+        );
+        buff += &self.list(
+            summary.entry_urls.iter().map(|(url, count)| (url, count)).collect(),
+            "entry URL",
+            limit,
+        );
+        buff += &self.list(
+            summary.exit_urls.iter().map(|(url, count)| (url, count)).collect(),
+            "exit URL",
+            limit,
+        );
+        buff
+    }
+
+    pub fn params(&self, params: &[ParamReport], unique: usize, global: bool) -> String {
+        let color = Colour::Yellow;
+        let indent = if global { "" } else { "\t" };
+        let mut buff = format!(
+            "{}{} {}{}: ({} unique)\n",
+            indent,
+            params.len(),
+            self.opt_color("Query parameter", &color, true),
+            if global { "s overall" } else { "s" },
+            self.opt_color(&unique.to_string(), &color, true),
+        );
+        for param in params {
+            let ips = match param.ips {
+                Some(ips) => format!(", {ips} IPs"),
+                None => String::new(),
+            };
+            let fuzzing = match (global, param.fuzzers.is_empty()) {
+                (_, true) => String::new(),
+                (false, false) => format!(" {}", self.opt_color("FUZZING", &Colour::Red, true)),
+                (true, false) => format!(
+                    " {} {}",
+                    self.opt_color("fuzzed by", &Colour::Red, true),
+                    param.fuzzers.join(", ")
+                ),
+            };
+            buff += &format!(
+                "{}\t{} ({}, {} distinct values{}){}\n",
+                indent,
+                self.opt_color(&param.name, &color, false),
+                self.opt_color(&format!("{}x", param.count), &color, true),
+                self.opt_color(
+                    &format!("{}{}", param.distinct_values, if param.values_capped { "+" } else { "" }),
+                    &color,
+                    true
+                ),
+                ips,
+                fuzzing,
+            );
+        }
+        buff += "\n";
+        buff
+    }
+
+    pub fn anomalies(
+        &self,
+        anomalies: &[Anomaly],
+        current: (DateTime<Local>, DateTime<Local>),
+        baseline: (DateTime<Local>, DateTime<Local>),
+        limit: usize,
+    ) -> String {
+        let color = Colour::Red;
+        let format = "%Y-%m-%d %H:%M";
+        let mut buff = format!(
+            "{} ({} - {} vs {} - {}):\n",
+            self.opt_color("Changes against baseline", &color, true),
+            current.0.format(format),
+            current.1.format(format),
+            baseline.0.format(format),
+            baseline.1.format(format),
+        );
+        if anomalies.is_empty() {
+            buff += "\tNothing above the thresholds\n\n";
+            return buff;
+        }
+        for (dimension, title) in [
+            (Dimension::Ip, "IP"),
+            (Dimension::Url, "URL"),
+            (Dimension::Ua, "UA"),
+        ] {
+            let rows: Vec<&Anomaly> = anomalies
+                .iter()
+                .filter(|anomaly| anomaly.dimension == dimension)
+                .take(limit)
+                .collect();
+            if rows.is_empty() {
+                continue;
+            }
+            buff += &format!("\t{}s:\n", self.opt_color(title, &color, true));
+            for anomaly in rows {
+                let growth = match anomaly.growth {
+                    Some(growth) => format!("share x{growth:.1}"),
+                    None => "new".to_string(),
+                };
+                buff += &format!(
+                    "\t\t{} ({} requests, expected {:.1}, {:.2}% -> {:.2}%, {}, z {:.1})\n",
+                    self.opt_color(&anomaly.value, &color, false),
+                    self.opt_color(&anomaly.current.to_string(), &color, true),
+                    anomaly.expected,
+                    anomaly.baseline_share * 100.0,
+                    anomaly.current_share * 100.0,
+                    self.opt_color(&growth, &color, true),
+                    anomaly.z_score,
+                );
+            }
+        }
+        buff += "\n";
+        buff
+    }
+
+    /// Shows `ip_limit` IP rows and `value_limit` rows of the other sections, 0 shows all.
+    pub fn diff(&self, diff: &SnapshotDiff, ip_limit: usize, value_limit: usize) -> String {
+        let mut buff = String::new();
+        for (entries, title, limit) in [
+            (Some(&diff.ips), "IP", ip_limit),
+            (diff.urls.as_ref(), "URL", value_limit),
+            (diff.uas.as_ref(), "UA", value_limit),
+            (diff.referrer_domains.as_ref(), "Referring domain", value_limit),
+        ] {
+            if let Some(entries) = entries {
+                buff += &self.diff_section(entries, title, limit);
+            }
+        }
+        buff
+    }
+
+    fn diff_section(&self, entries: &[DiffEntry], title: &str, limit: usize) -> String {
+        let count = |change| entries.iter().filter(|entry| entry.change == change).count();
+        let mut buff = format!(
+            "{}s: {} added, {} removed, {} changed\n",
+            self.opt_color(title, &Colour::Cyan, true),
+            self.opt_color(&count(Change::Added).to_string(), &Colour::Green, true),
+            self.opt_color(&count(Change::Removed).to_string(), &Colour::Red, true),
+            self.opt_color(&count(Change::Changed).to_string(), &Colour::Yellow, true),
+        );
+        let limit = if limit == 0 { entries.len() } else { limit };
+        for entry in entries.iter().take(limit) {
+            let (sign, color) = match entry.change {
+                Change::Added => ("+", Colour::Green),
+                Change::Removed => ("-", Colour::Red),
+                Change::Changed => ("~", Colour::Yellow),
+            };
+            let percent = match entry.change {
+                Change::Changed => format!(", {:+.0}%", entry.delta as f64 * 100.0 / entry.old as f64),
+                _ => String::new(),
+            };
+            let bytes = match (entry.old_bytes, entry.new_bytes) {
+                (Some(old), Some(new)) => format!(", {} -> {}", Self::human_bytes(old), Self::human_bytes(new)),
+                _ => String::new(),
+            };
+            buff += &format!(
+                "\t{} {} {} -> {} ({}{}{})\n",
+                self.opt_color(sign, &color, true),
+                self.opt_color(&entry.value, &color, false),
+                entry.old,
+                entry.new,
+                self.opt_color(&format!("{:+}", entry.delta), &color, true),
+                percent,
+                bytes,
+            );
+        }
+        buff += "\n";
+        buff
+    }
+
+    pub fn logins(&self, report: &LoginReport, limit: usize) -> String {
+        let color = Colour::Red;
+        let mut buff = format!(
+            "{} ({}):\n",
+            self.opt_color("Login attempts", &color, true),
+            report.paths.join(", "),
+        );
+        buff += &self.login_stats(&report.ips, "IP", limit);
+        buff += &self.login_stats(&report.subnets, "Subnet", limit);
+        if !report.distributed.is_empty() {
+            buff += &format!("\t{}:\n", self.opt_color("Distributed attacks", &color, true));
+            for attack in report.distributed.iter().take(limit) {
+                buff += &format!(
+                    "\t\t{} ({} IPs below the threshold, {} failures)\n",
+                    self.opt_color(&attack.subnet, &color, false),
+                    self.opt_color(&attack.ips.to_string(), &color, true),
+                    attack.failures,
+                );
+            }
+        }
+        if !report.stuffing.is_empty() {
+            buff += &format!("\t{}:\n", self.opt_color("Credential stuffing", &color, true));
+            for attack in report.stuffing.iter().take(limit) {
+                buff += &format!(
+                    "\t\t{} from {} ({} IPs below the threshold in {} subnets, {} failures)\n",
+                    self.opt_color(&attack.path, &color, false),
+                    attack.start,
+                    self.opt_color(&attack.ips.to_string(), &color, true),
+                    attack.subnets,
+                    attack.failures,
+                );
+            }
+        }
+        if report.low_volume_ips > 0 {
+            buff += &format!(
+                "\t{} more IPs failed {} logins below the threshold\n",
+                self.opt_color(&report.low_volume_ips.to_string(), &color, true),
+                report.low_volume_failures,
+            );
+        }
+        buff += "\n";
+        buff
+    }
+
+    pub fn clusters(&self, clusters: &[Cluster], limit: usize) -> String {
+        let color = Colour::Purple;
+        let mut buff = format!(
+            "{} {}:\n",
+            clusters.len(),
+            self.opt_color("IP clusters with a shared fingerprint", &color, true),
+        );
+        for cluster in clusters.iter().take(limit) {
+            let rare = match cluster.rare_ua {
+                true => format!(" {}", self.opt_color("RARE UA", &Colour::Red, true)),
+                false => String::new(),
+            };
+            buff += &format!(
+                "\t{} IPs, {} requests{}\n",
+                self.opt_color(&cluster.ips.len().to_string(), &color, true),
+                self.opt_color(&cluster.requests.to_string(), &color, true),
+                rare,
+            );
+            buff += &format!("\t\tUA: {}\n", self.opt_color(&cluster.ua, &color, false));
+            if !cluster.urls.is_empty() {
+                buff += &format!("\t\tURLs: {}\n", cluster.urls.join(" -> "));
+            }
+            if let Some(referrer) = &cluster.referrer {
+                buff += &format!("\t\tReferrer: {referrer}\n");
+            }
+            if let Some((min, max)) = cluster.gap_secs {
+                buff += &format!(
+                    "\t\tGap between requests: {} - {}\n",
+                    Self::human_duration(min as i64),
+                    Self::human_duration(max as i64),
+                );
+            }
+            let spread = |values: &[(String, usize)]| {
+                values
+                    .iter()
+                    .take(limit)
+                    .map(|(value, count)| format!("{value} ({count})"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            buff += &format!("\t\tSubnets ({}): {}\n", cluster.subnets.len(), spread(&cluster.subnets));
+            if !cluster.countries.is_empty() {
+                buff += &format!(
+                    "\t\tCountries ({}): {}\n",
+                    cluster.countries.len(),
+                    spread(&cluster.countries),
+                );
+            }
+            let mut members = cluster.ips.iter().take(limit).cloned().collect::<Vec<_>>().join(", ");
+            if cluster.ips.len() > limit {
+                members += &format!(" and {} more", cluster.ips.len() - limit);
+            }
+            buff += &format!("\t\tMembers: {members}\n");
+        }
+        buff += "\n";
+        buff
+    }
+
+    fn login_stats(&self, stats: &[LoginStats], title: &str, limit: usize) -> String {
+        if stats.is_empty() {
+            return String::new();
+        }
+        let color = Colour::Red;
+        let mut buff = format!("\t{}s:\n", self.opt_color(title, &color, true));
+        for stat in stats.iter().take(limit) {
+            let ips = match stat.ips {
+                1 => String::new(),
+                ips => format!(", {ips} IPs"),
+            };
+            let success = match stat.success_after_failures {
+                0 => String::new(),
+                count => format!(
+                    " {}",
+                    self.opt_color(&format!("{count} SUCCESS AFTER FAILURES"), &Colour::Red, true)
+                ),
+            };
+            buff += &format!(
+                "\t\t{} ({} failed, {} succeeded of {} attempts, {:.1}/min{}){}\n",
+                self.opt_color(&stat.key, &color, false),
+                self.opt_color(&stat.failures.to_string(), &color, true),
+                stat.successes,
+                stat.attempts,
+                stat.attempts_per_minute,
+                ips,
+                success,
+            );
+        }
+        buff
+    }
+
+    pub fn human_duration(secs: i64) -> String {
+        match secs {
+            s if s < 60 => format!("{s}s"),
+            s if s < 3600 => format!("{}m {}s", s / 60, s % 60),
+            s => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        }
+    }
+
     pub fn location(&self, location: IpLocation) -> String{
         let color = Colour::Red;
         format!("\t{}", self.opt_color(&location.to_string(), &color, true))
@@ -0,0 +1,181 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Distinct values kept per parameter, enough to spot fuzzing without letting
+/// a single parameter grow without bound. Also the upper limit of --fuzz-threshold.
+pub const MAX_TRACKED_VALUES: usize = 1000;
+
+/// Splits the query string of a URL or request line into decoded name/value pairs.
+pub fn parse_query(url: &str) -> Vec<(String, String)> {
+    let target = url.split(' ').nth(1).unwrap_or(url);
+    let Some((_, query)) = target.split_once('?') else {
+        return Vec::new();
+    };
+    let query = query.split('#').next().unwrap_or_default();
+
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decodes `%XX` escapes and `+` as space, invalid escapes are kept as is.
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push(high << 4 | low);
+                    i += 3;
+                    continue;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParamUsage {
+    pub count: usize,
+    pub values: HashSet<String>,
+}
+
+impl ParamUsage {
+    pub fn add(&mut self, value: &str) {
+        self.count += 1;
+        if self.values.len() < MAX_TRACKED_VALUES && !self.values.contains(value) {
+            self.values.insert(value.to_string());
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GlobalParamUsage {
+    pub usage: ParamUsage,
+    pub ips: HashSet<String>,
+    // distinct values per IP until the IP reaches the fuzzing threshold
+    ip_values: HashMap<String, HashSet<String>>,
+    pub fuzzers: HashSet<String>,
+}
+
+/// Query parameter usage across all clients.
+#[derive(Debug)]
+pub struct ParamStats {
+    pub fuzz_threshold: usize,
+    pub params: HashMap<String, GlobalParamUsage>,
+}
+
+impl ParamStats {
+    pub fn new(fuzz_threshold: usize) -> Self {
+        Self {
+            fuzz_threshold,
+            params: HashMap::new(),
+        }
+    }
+
+    pub fn collect(&mut self, ip: &str, params: &[(String, String)]) {
+        for (name, value) in params {
+            let param = self.params.entry(name.clone()).or_default();
+            param.usage.add(value);
+            if !param.ips.contains(ip) {
+                param.ips.insert(ip.to_string());
+            }
+            if param.fuzzers.contains(ip) {
+                continue;
+            }
+
+            let values = param.ip_values.entry(ip.to_string()).or_default();
+            values.insert(value.clone());
+            if values.len() >= self.fuzz_threshold {
+                param.ip_values.remove(ip);
+                param.fuzzers.insert(ip.to_string());
+            }
+        }
+    }
+
+    pub fn top(&self, limit: usize) -> Vec<(&String, &GlobalParamUsage)> {
+        let mut vec: Vec<(&String, &GlobalParamUsage)> = self.params.iter().collect();
+        vec.sort_unstable_by(|a, b| {
+            b.1.usage
+                .count
+                .cmp(&a.1.usage.count)
+                .then_with(|| a.0.cmp(b.0))
+        });
+        vec.truncate(limit);
+        vec
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamReport {
+    pub name: String,
+    pub count: usize,
+    pub distinct_values: usize,
+    /// Distinct values stop being counted at the tracking limit
+    pub values_capped: bool,
+    pub ips: Option<usize>,
+    pub fuzzers: Vec<String>,
+}
+
+impl ParamReport {
+    /// Parameters of a single IP, most used first.
+    pub fn for_ip(
+        params: &HashMap<String, ParamUsage>,
+        limit: usize,
+        fuzz_threshold: usize,
+        ip: &str,
+    ) -> Vec<Self> {
+        let mut vec: Vec<Self> = params
+            .iter()
+            .map(|(name, usage)| Self {
+                name: name.clone(),
+                count: usage.count,
+                distinct_values: usage.values.len(),
+                values_capped: usage.values.len() >= MAX_TRACKED_VALUES,
+                ips: None,
+                fuzzers: match usage.values.len() >= fuzz_threshold {
+                    true => vec![ip.to_string()],
+                    false => Vec::new(),
+                },
+            })
+            .collect();
+        vec.sort_unstable_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        vec.truncate(limit);
+        vec
+    }
+
+    pub fn global(stats: &ParamStats, limit: usize) -> Vec<Self> {
+        stats
+            .top(limit)
+            .into_iter()
+            .map(|(name, param)| {
+                let mut fuzzers: Vec<String> = param.fuzzers.iter().cloned().collect();
+                fuzzers.sort_unstable();
+                Self {
+                    name: name.clone(),
+                    count: param.usage.count,
+                    distinct_values: param.usage.values.len(),
+                    values_capped: param.usage.values.len() >= MAX_TRACKED_VALUES,
+                    ips: Some(param.ips.len()),
+                    fuzzers,
+                }
+            })
+            .collect()
+    }
+}
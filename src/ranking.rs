@@ -0,0 +1,90 @@
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::ip_info::IpInfo;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortKey {
+    /// Number of requests
+    #[default]
+    Count,
+    /// Total response bytes sent
+    Bytes,
+    /// Average requests per minute
+    Rpm,
+    /// Requests per minute in the last hour
+    RpmLastHour,
+    /// Responses with status 400 and above
+    Errors,
+    /// Distinct URLs requested
+    Urls,
+    /// Total server time spent on the requests
+    ServerTime,
+    /// Suspicion score
+    Score,
+}
+
+impl SortKey {
+    /// Keys that are only known after the full pass, so every IP has to be
+    /// collected before the top list can be selected.
+    pub fn needs_full_pass(&self) -> bool {
+        !matches!(self, SortKey::Count | SortKey::Bytes)
+    }
+
+    fn value(&self, ip_info: &IpInfo, latest_timestamp: DateTime<Local>) -> f64 {
+        match self {
+            SortKey::Count => ip_info.count as f64,
+            SortKey::Bytes => ip_info.total_bytes as f64,
+            SortKey::Rpm => ip_info.average_rpm(),
+            SortKey::RpmLastHour => ip_info.average_rpm_last_hour(latest_timestamp),
+            SortKey::Errors => ip_info.error_count() as f64,
+            SortKey::Urls => ip_info.url_map.len() as f64,
+            SortKey::ServerTime => ip_info.latency.sum(),
+            SortKey::Score => ip_info.score.as_ref().map_or(0.0, |score| score.total),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Sorts IPs by the given keys, later keys break ties of earlier ones and the
+/// IP itself breaks any remaining ties.
+pub fn rank_ips<'a>(
+    ip_map: &'a HashMap<String, IpInfo>,
+    keys: &[SortKey],
+    order: SortOrder,
+    latest_timestamp: DateTime<Local>,
+) -> Vec<(&'a String, &'a IpInfo)> {
+    let mut ranked: Vec<(&String, &IpInfo, Vec<f64>)> = ip_map
+        .iter()
+        .map(|(ip, info)| {
+            let values = keys
+                .iter()
+                .map(|key| key.value(info, latest_timestamp))
+                .collect();
+            (ip, info, values)
+        })
+        .collect();
+
+    ranked.sort_unstable_by(|(a_ip, _, a_values), (b_ip, _, b_values)| {
+        let by_keys = a_values
+            .iter()
+            .zip(b_values)
+            .map(|(a, b)| a.total_cmp(b))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal);
+        let by_keys = match order {
+            SortOrder::Asc => by_keys,
+            SortOrder::Desc => by_keys.reverse(),
+        };
+        by_keys.then_with(|| a_ip.cmp(b_ip))
+    });
+
+    ranked.into_iter().map(|(ip, info, _)| (ip, info)).collect()
+}
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Fewer gaps than this say nothing about the rhythm of a client.
+const MIN_INTERVALS: usize = 5;
+
+/// Inter-arrival statistics of the requests of one client.
+#[derive(Serialize, Debug, Clone)]
+pub struct TimingStats {
+    pub intervals: usize,
+    pub mean_gap_secs: f64,
+    /// Coefficient of variation of the gaps, near 0 for scripted clients
+    pub cv: f64,
+    /// Shannon entropy of the gaps bucketed on a log2 scale, in bits
+    pub gap_entropy: f64,
+    /// Most common gap in seconds and the share of gaps within 10% of it
+    pub period_secs: i64,
+    pub period_share: f64,
+    pub regular: bool,
+}
+
+impl TimingStats {
+    /// Requests within the same second count as one, logs only have second resolution
+    /// and a page with its assets would otherwise hide the rhythm between pages.
+    pub fn new(timestamps: &[DateTime<Local>], max_cv: f64) -> Option<Self> {
+        let mut seconds: Vec<i64> = timestamps.iter().map(DateTime::timestamp).collect();
+        seconds.sort_unstable();
+        seconds.dedup();
+        let gaps: Vec<i64> = seconds.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        if gaps.len() < MIN_INTERVALS {
+            return None;
+        }
+
+        let n = gaps.len() as f64;
+        let mean = gaps.iter().sum::<i64>() as f64 / n;
+        let variance = gaps
+            .iter()
+            .map(|&gap| (gap as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let cv = variance.sqrt() / mean;
+
+        let mut bins: HashMap<u32, usize> = HashMap::new();
+        for &gap in &gaps {
+            *bins.entry(gap.ilog2()).or_insert(0) += 1;
+        }
+        let gap_entropy = bins
+            .values()
+            .map(|&count| {
+                let p = count as f64 / n;
+                p * (1.0 / p).log2()
+            })
+            .sum::<f64>();
+
+        let mut counts: HashMap<i64, usize> = HashMap::new();
+        for &gap in &gaps {
+            *counts.entry(gap).or_insert(0) += 1;
+        }
+        let period_secs = counts
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(&gap, _)| gap)
+            .unwrap_or_default();
+        let tolerance = (period_secs as f64 * 0.1).max(1.0);
+        let period_share = gaps
+            .iter()
+            .filter(|&&gap| (gap - period_secs).abs() as f64 <= tolerance)
+            .count() as f64
+            / n;
+
+        Some(Self {
+            intervals: gaps.len(),
+            mean_gap_secs: mean,
+            cv,
+            gap_entropy,
+            period_secs,
+            period_share,
+            regular: cv <= max_cv,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamps(seconds: &[i64]) -> Vec<DateTime<Local>> {
+        seconds
+            .iter()
+            .map(|&second| {
+                DateTime::from_timestamp(second, 0)
+                    .unwrap()
+                    .with_timezone(&Local)
+            })
+            .collect()
+    }
+
+    /// Timestamps with the given gaps between them, starting at 1000.
+    fn with_gaps(gaps: &[i64]) -> Vec<DateTime<Local>> {
+        let mut seconds = vec![1000];
+        for gap in gaps {
+            seconds.push(seconds.last().unwrap() + gap);
+        }
+        timestamps(&seconds)
+    }
+
+    #[test]
+    fn too_few_gaps_say_nothing() {
+        assert!(TimingStats::new(&[], 0.1).is_none());
+        assert!(TimingStats::new(&timestamps(&[1000]), 0.1).is_none());
+        assert!(TimingStats::new(&timestamps(&[1000, 1060]), 0.1).is_none());
+        assert!(TimingStats::new(&with_gaps(&[60; MIN_INTERVALS - 1]), 0.1).is_none());
+        assert!(TimingStats::new(&with_gaps(&[60; MIN_INTERVALS]), 0.1).is_some());
+    }
+
+    #[test]
+    fn requests_in_the_same_second_count_once() {
+        assert!(TimingStats::new(&timestamps(&[1000; 20]), 0.1).is_none());
+        let mut seconds: Vec<i64> = (0..6).map(|i| 1000 + i * 60).collect();
+        seconds.extend([1000, 1060, 1060, 1120]);
+        let stats = TimingStats::new(&timestamps(&seconds), 0.1).unwrap();
+        assert_eq!(stats.intervals, 5);
+        assert_eq!(stats.cv, 0.0);
+    }
+
+    #[test]
+    fn constant_gaps_are_regular() {
+        let stats = TimingStats::new(&with_gaps(&[60; 10]), 0.1).unwrap();
+        assert_eq!(stats.mean_gap_secs, 60.0);
+        assert_eq!(stats.cv, 0.0);
+        assert_eq!(stats.gap_entropy, 0.0);
+        assert_eq!((stats.period_secs, stats.period_share), (60, 1.0));
+        assert!(stats.regular);
+    }
+
+    #[test]
+    fn cv_is_compared_against_max_cv() {
+        // mean 20, standard deviation 10
+        let timestamps = with_gaps(&[10, 30, 10, 30, 10, 30]);
+        let stats = TimingStats::new(&timestamps, 0.5).unwrap();
+        assert!((stats.cv - 0.5).abs() < 1e-9);
+        assert!(stats.regular);
+        assert!(!TimingStats::new(&timestamps, 0.49).unwrap().regular);
+    }
+
+    #[test]
+    fn period_prefers_the_shorter_gap_on_ties() {
+        let stats = TimingStats::new(&with_gaps(&[10, 30, 10, 30, 10, 30]), 0.5).unwrap();
+        assert_eq!(stats.period_secs, 10);
+        assert_eq!(stats.period_share, 0.5);
+        // 10 and 30 fall into different log2 buckets
+        assert!((stats.gap_entropy - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    anomaly::Anomaly,
+    clusters::Cluster,
+    crawler_verify::CrawlerCheck,
+    global_stats::GlobalStats,
+    ip_info::IpInfo,
+    ip_location::IpLocation,
+    login::LoginReport,
+    query_params::ParamReport,
+    ranking::SortKey,
+    regularity::TimingStats,
+    robots::RobotsFinding,
+    scanning::{MissingPathReport, ScanFinding},
+    scoring::Score,
+    sessions::SessionSummary,
+    sketch::{LatencySummary, SketchSummary, TDigest},
+    timeline::{Timeline, TrafficRow},
+    traps::TrapHit,
+    ua_classifier::UaClass,
+    ua_rotation::UaRotation,
+};
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub processed_lines: usize,
+    pub ips: Vec<IpReport>,
+    pub traffic: Option<Vec<TrafficRow>>,
+    pub global: Option<GlobalReport>,
+    pub approximation: Option<SketchSummary>,
+    pub params: Option<Vec<ParamReport>>,
+    pub anomalies: Option<Vec<Anomaly>>,
+    pub logins: Option<LoginReport>,
+    pub missing_paths: Option<Vec<MissingPathReport>>,
+    pub clusters: Option<Vec<Cluster>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct GlobalReport {
+    pub urls: Vec<DimensionReport>,
+    pub uas: Vec<DimensionReport>,
+    pub referrer_domains: Vec<DimensionReport>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DimensionReport {
+    pub value: String,
+    pub count: usize,
+    pub ips: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub latency: Option<LatencySummary>,
+}
+
+impl GlobalReport {
+    pub fn new(stats: &GlobalStats, limit: usize, sort: SortKey) -> Self {
+        let dimension = |map, latency: Option<&HashMap<String, TDigest>>| {
+            GlobalStats::top(map, limit, sort)
+                .into_iter()
+                .map(|(value, stat)| DimensionReport {
+                    value: value.clone(),
+                    count: stat.count,
+                    ips: stat.ips.len(),
+                    bytes: stat.bytes,
+                    max_bytes: stat.max_bytes,
+                    latency: latency
+                        .and_then(|latency| latency.get(value))
+                        .and_then(LatencySummary::new),
+                })
+                .collect()
+        };
+        Self {
+            urls: dimension(&stats.urls, Some(&stats.url_latency)),
+            uas: dimension(&stats.uas, None),
+            referrer_domains: dimension(&stats.referrer_domains, None),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct IpReport {
+    pub ip: String,
+    pub count: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub latency: Option<LatencySummary>,
+    pub average_rpm: f64,
+    pub rpm_last_hour: f64,
+    pub last_access: Option<DateTime<Local>>,
+    pub location: Option<IpLocation>,
+    pub urls: Vec<(String, usize)>,
+    pub referrers: Vec<(String, usize)>,
+    pub uas: Vec<(String, usize)>,
+    pub members: Vec<(String, usize)>,
+    pub sessions: Option<SessionSummary>,
+    pub params: Vec<ParamReport>,
+    pub timing: Option<TimingStats>,
+    pub ua_rotation: Option<UaRotation>,
+    /// Class of the dominant user agent and its share of requests
+    pub client: Option<UaClass>,
+    pub client_share: Option<f64>,
+    pub crawler_check: Option<CrawlerCheck>,
+    pub trap: Option<TrapHit>,
+    pub reputation: Vec<String>,
+    pub rule_hits: Vec<(String, usize)>,
+    pub score: Option<Score>,
+    pub scan: Option<ScanFinding>,
+    pub robots: Option<RobotsFinding>,
+    pub timeline: Option<Timeline>,
+}
+
+impl IpReport {
+    pub fn new(
+        ip: &str,
+        ip_info: &IpInfo,
+        latest_timestamp: DateTime<Local>,
+        timeline: Option<Timeline>,
+        top_params: usize,
+    ) -> Self {
+        Self {
+            ip: ip.to_string(),
+            count: ip_info.count,
+            total_bytes: ip_info.total_bytes,
+            max_bytes: ip_info.max_bytes,
+            latency: LatencySummary::new(&ip_info.latency),
+            average_rpm: ip_info.average_rpm(),
+            rpm_last_hour: ip_info.average_rpm_last_hour(latest_timestamp),
+            last_access: ip_info.last_timestamp(),
+            location: ip_info.location_data.clone(),
+            urls: top_counts(&ip_info.url_map, top_params),
+            referrers: top_counts(&ip_info.referrer_map, top_params),
+            uas: top_counts(&ip_info.ua_map, top_params),
+            members: top_counts(&ip_info.members, ip_info.members.len()),
+            sessions: None,
+            params: Vec::new(),
+            timing: None,
+            ua_rotation: None,
+            client: None,
+            client_share: None,
+            crawler_check: ip_info.crawler_check.clone(),
+            trap: ip_info.trap.clone(),
+            reputation: ip_info.reputation.clone(),
+            rule_hits: top_counts(&ip_info.rule_hits, ip_info.rule_hits.len()),
+            score: ip_info.score.clone(),
+            scan: None,
+            robots: None,
+            timeline,
+        }
+    }
+}
+
+fn top_counts(map: &HashMap<String, usize>, limit: usize) -> Vec<(String, usize)> {
+    let mut vec: Vec<(String, usize)> = map.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    vec.truncate(limit);
+    vec
+}
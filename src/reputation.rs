@@ -0,0 +1,247 @@
+use std::{
+    error::Error,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use crate::grouping::ip_to_u128;
+
+/// Parses `[NAME=]PATH` of --reputation, the name defaults to the file stem.
+pub fn parse_list(value: &str) -> Result<(String, PathBuf), String> {
+    let (name, path) = match value.split_once('=') {
+        Some((name, path)) => (name.trim().to_string(), PathBuf::from(path)),
+        None => {
+            let path = PathBuf::from(value);
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            (name, path)
+        }
+    };
+    if name.is_empty() {
+        return Err(format!("'{value}' has no list name"));
+    }
+    Ok((name, path))
+}
+
+/// IPs and networks of a blocklist, as merged ranges in the IPv6 space of `ip_to_u128`.
+#[derive(Debug)]
+struct List {
+    name: String,
+    ranges: Vec<(u128, u128)>,
+}
+
+impl List {
+    /// One IP or CIDR per line, text after `#` or `;` is a comment (Spamhaus DROP style).
+    fn load(name: &str, path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read reputation list {}: {err}", path.display()))?;
+        let mut ranges = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split(['#', ';']).next().unwrap_or_default();
+            let Some(value) = line.split_whitespace().next() else {
+                continue;
+            };
+            // lines that are not addresses, like list headers, are skipped
+            let range = Self::range(value)
+                .map_err(|err| format!("{}:{}: {err}", path.display(), number + 1))?;
+            if let Some(range) = range {
+                ranges.push(range);
+            }
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            ranges: Self::merge(ranges),
+        })
+    }
+
+    /// Sorted ranges with overlapping and adjacent ones joined.
+    fn merge(mut ranges: Vec<(u128, u128)>) -> Vec<(u128, u128)> {
+        ranges.sort_unstable();
+        let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// None for values that are not an IP or CIDR, an error for a prefix longer than the address.
+    fn range(value: &str) -> Result<Option<(u128, u128)>, String> {
+        let parsed = match value.split_once('/') {
+            Some((ip, prefix)) => ip
+                .parse::<IpAddr>()
+                .ok()
+                .zip(prefix.parse::<u32>().ok().map(Some)),
+            None => value.parse::<IpAddr>().ok().map(|ip| (ip, None)),
+        };
+        let Some((ip, prefix)) = parsed else {
+            return Ok(None);
+        };
+        let bits = match ip {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.unwrap_or(bits);
+        if prefix > bits {
+            return Err(format!(
+                "prefix /{prefix} of {value} is longer than {bits} bits"
+            ));
+        }
+        let host_mask = u128::MAX.checked_shr(128 - (bits - prefix)).unwrap_or(0);
+        let start = ip_to_u128(&ip) & !host_mask;
+        Ok(Some((start, start | host_mask)))
+    }
+
+    fn contains(&self, ip: u128) -> bool {
+        let idx = self.ranges.partition_point(|(start, _)| *start <= ip);
+        self.ranges[..idx].last().is_some_and(|(_, end)| ip <= *end)
+    }
+}
+
+/// Local copies of IP reputation lists such as Spamhaus DROP, Tor exit nodes or FireHOL.
+#[derive(Debug)]
+pub struct Reputation {
+    lists: Vec<List>,
+}
+
+impl Reputation {
+    pub fn load(lists: &[(String, PathBuf)]) -> Result<Self, Box<dyn Error>> {
+        let lists = lists
+            .iter()
+            .map(|(name, path)| List::load(name, path))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { lists })
+    }
+
+    /// Names of the lists containing the IP.
+    pub fn tags(&self, ip: &str) -> Vec<String> {
+        let Ok(addr) = ip.parse::<IpAddr>() else {
+            return Vec::new();
+        };
+        let ip = ip_to_u128(&addr);
+        self.lists
+            .iter()
+            .filter(|list| list.contains(ip))
+            .map(|list| list.name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> u128 {
+        ip_to_u128(&value.parse().unwrap())
+    }
+
+    fn list(values: &[&str]) -> List {
+        List {
+            name: "test".to_string(),
+            ranges: List::merge(
+                values
+                    .iter()
+                    .filter_map(|value| List::range(value).unwrap())
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn single_addresses_and_prefixes() {
+        assert_eq!(
+            List::range("10.0.0.1"),
+            Ok(Some((ip("10.0.0.1"), ip("10.0.0.1"))))
+        );
+        assert_eq!(
+            List::range("10.0.0.1/32"),
+            Ok(Some((ip("10.0.0.1"), ip("10.0.0.1"))))
+        );
+        assert_eq!(
+            List::range("10.0.0.77/24"),
+            Ok(Some((ip("10.0.0.0"), ip("10.0.0.255"))))
+        );
+        assert_eq!(
+            List::range("2001:db8::1/128"),
+            Ok(Some((ip("2001:db8::1"), ip("2001:db8::1"))))
+        );
+        assert_eq!(
+            List::range("2001:db8::/32"),
+            Ok(Some((
+                ip("2001:db8::"),
+                ip("2001:db8:ffff:ffff:ffff:ffff:ffff:ffff")
+            )))
+        );
+    }
+
+    #[test]
+    fn zero_prefix_covers_the_whole_family() {
+        assert_eq!(
+            List::range("1.2.3.4/0"),
+            Ok(Some((ip("0.0.0.0"), ip("255.255.255.255"))))
+        );
+        assert_eq!(List::range("::/0"), Ok(Some((0, u128::MAX))));
+    }
+
+    #[test]
+    fn oversized_prefix_is_an_error() {
+        assert!(List::range("10.0.0.1/33").is_err());
+        assert!(List::range("2001:db8::/129").is_err());
+    }
+
+    #[test]
+    fn oversized_prefix_fails_with_the_line_number() {
+        let path = std::env::temp_dir().join(format!("reputation-{}.txt", std::process::id()));
+        fs::write(&path, "; header\n10.0.0.0/8\n10.0.0.1/40 ; typo\n").unwrap();
+        let err = List::load("test", &path).unwrap_err().to_string();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            err.ends_with(":3: prefix /40 of 10.0.0.1/40 is longer than 32 bits"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_skipped() {
+        assert_eq!(List::range("Spamhaus"), Ok(None));
+        assert_eq!(List::range("10.0.0.0/x"), Ok(None));
+        assert_eq!(List::range("10.0.0/24"), Ok(None));
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_ranges_merge() {
+        let list = list(&["10.0.1.0/24", "10.0.0.0/24", "10.0.0.128/25", "10.0.3.0/24"]);
+        assert_eq!(
+            list.ranges,
+            vec![
+                (ip("10.0.0.0"), ip("10.0.1.255")),
+                (ip("10.0.3.0"), ip("10.0.3.255")),
+            ]
+        );
+        assert!(list.contains(ip("10.0.1.200")));
+        assert!(!list.contains(ip("10.0.2.1")));
+        assert!(!list.contains(ip("9.255.255.255")));
+        assert!(!list.contains(ip("10.0.4.0")));
+    }
+
+    #[test]
+    fn full_space_merges_without_overflow() {
+        let list = list(&["::/0", "10.0.0.0/8", "::ffff:ffff:ffff/128"]);
+        assert_eq!(list.ranges, vec![(0, u128::MAX)]);
+        assert!(list.contains(u128::MAX));
+        assert!(list.contains(ip("10.1.2.3")));
+    }
+
+    #[test]
+    fn ipv4_and_ipv6_do_not_overlap() {
+        let list = list(&["0.0.0.0/0"]);
+        assert!(list.contains(ip("192.0.2.1")));
+        assert!(!list.contains(ip("2001:db8::1")));
+    }
+}
@@ -0,0 +1,265 @@
+use regex::Regex;
+use serde::Serialize;
+use std::{error::Error, fs, path::Path};
+
+use crate::{
+    ip_info::IpInfo,
+    ua_classifier::{self, UaCategory},
+};
+
+#[derive(Debug)]
+struct Rule {
+    allow: bool,
+    /// Length of the pattern, the longest matching rule wins
+    length: usize,
+    regex: Regex,
+}
+
+#[derive(Debug)]
+struct Group {
+    agent: String,
+    rules: Vec<Rule>,
+    crawl_delay: Option<f64>,
+}
+
+/// Rules of a robots.txt file, groups naming the same user agent are merged.
+#[derive(Debug)]
+pub struct Robots {
+    groups: Vec<Group>,
+}
+
+impl Robots {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read robots file {}: {err}", path.display()))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut current: Vec<usize> = Vec::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match field.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        current.clear();
+                        in_rules = false;
+                    }
+                    let agent = value.to_lowercase();
+                    let index = match groups.iter().position(|group| group.agent == agent) {
+                        Some(index) => index,
+                        None => {
+                            groups.push(Group {
+                                agent,
+                                rules: Vec::new(),
+                                crawl_delay: None,
+                            });
+                            groups.len() - 1
+                        }
+                    };
+                    current.push(index);
+                }
+                field @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // an empty disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    for &index in &current {
+                        groups[index].rules.push(Rule {
+                            allow: field == "allow",
+                            length: value.len(),
+                            regex: Self::pattern(value)?,
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    let delay = value.parse::<f64>().ok().filter(|delay| *delay > 0.0);
+                    for &index in &current {
+                        groups[index].crawl_delay = delay;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Self { groups })
+    }
+
+    /// `*` matches any characters and a trailing `$` anchors the end of the URL.
+    fn pattern(value: &str) -> Result<Regex, regex::Error> {
+        let (value, anchored) = match value.strip_suffix('$') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let pattern = regex::escape(value).replace(r"\*", ".*");
+        Regex::new(&format!("^{pattern}{}", if anchored { "$" } else { "" }))
+    }
+
+    /// Whether the group of the user agent allows the target of a request line.
+    pub fn allows(&self, ua: &str, request: &str) -> bool {
+        let target = request.split(' ').nth(1).unwrap_or(request);
+        self.group(ua).is_none_or(|group| group.allows(target))
+    }
+
+    /// The group with the longest agent contained in the user agent, else the `*` group.
+    fn group(&self, ua: &str) -> Option<&Group> {
+        let ua = ua.to_lowercase();
+        self.groups
+            .iter()
+            .filter(|group| group.agent != "*" && ua.contains(&group.agent))
+            .max_by_key(|group| group.agent.len())
+            .or_else(|| self.groups.iter().find(|group| group.agent == "*"))
+    }
+}
+
+impl Group {
+    /// Longest matching rule decides, allow wins ties.
+    fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| rule.regex.is_match(path))
+            .max_by_key(|rule| (rule.length, rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// A self-declared crawler fetching disallowed URLs or exceeding its Crawl-delay.
+#[derive(Serialize, Debug, Clone)]
+pub struct RobotsFinding {
+    /// User agent of the robots.txt group that applies
+    pub agent: String,
+    pub disallowed_requests: usize,
+    pub disallowed_urls: Vec<(String, usize)>,
+    pub crawl_delay: Option<f64>,
+    pub permitted_rpm: Option<f64>,
+    pub observed_rpm: f64,
+    pub too_fast: bool,
+}
+
+impl RobotsFinding {
+    /// Reports IPs whose dominant user agent is a search engine, SEO crawler or bot. Disallowed
+    /// URLs were checked on the raw URLs during the full pass, so --collapse-ids does not hide them.
+    pub fn new(ip_info: &IpInfo, robots: &Robots, top: usize) -> Option<Self> {
+        let (class, _) = ua_classifier::dominant(&ip_info.ua_map)?;
+        if !matches!(
+            class.category,
+            UaCategory::SearchEngine | UaCategory::SeoCrawler | UaCategory::Bot
+        ) {
+            return None;
+        }
+        let (ua, _) = ip_info
+            .ua_map
+            .iter()
+            .max_by_key(|(ua, count)| (*count, *ua))?;
+        let group = robots.group(ua)?;
+
+        let mut disallowed_urls: Vec<(String, usize)> = ip_info
+            .disallowed_map
+            .iter()
+            .map(|(request, count)| (request.clone(), *count))
+            .collect();
+        let disallowed_requests = disallowed_urls.iter().map(|(_, count)| count).sum();
+        disallowed_urls.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        disallowed_urls.truncate(top);
+
+        let permitted_rpm = group.crawl_delay.map(|delay| 60.0 / delay);
+        let observed_rpm = ip_info.average_rpm();
+        let too_fast = permitted_rpm.is_some_and(|permitted| observed_rpm > permitted);
+        if disallowed_requests == 0 && !too_fast {
+            return None;
+        }
+        Some(Self {
+            agent: group.agent.clone(),
+            disallowed_requests,
+            disallowed_urls,
+            crawl_delay: group.crawl_delay,
+            permitted_rpm,
+            observed_rpm,
+            too_fast,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allows(robots: &str, ua: &str, path: &str) -> bool {
+        Robots::parse(robots)
+            .unwrap()
+            .allows(ua, &format!("GET {path} HTTP/1.1"))
+    }
+
+    #[test]
+    fn longest_match_wins() {
+        let robots = "User-agent: *\nDisallow: /shop\nAllow: /shop/public\n";
+        assert!(!allows(robots, "AnyBot", "/shop/cart"));
+        assert!(allows(robots, "AnyBot", "/shop/public/item"));
+    }
+
+    #[test]
+    fn allow_wins_at_equal_length() {
+        let robots = "User-agent: *\nDisallow: /page\nAllow: /page\n";
+        assert!(allows(robots, "AnyBot", "/page"));
+        let robots = "User-agent: *\nAllow: /page\nDisallow: /page\n";
+        assert!(allows(robots, "AnyBot", "/page"));
+    }
+
+    #[test]
+    fn wildcard_matches_any_characters() {
+        let robots = "User-agent: *\nDisallow: /*.pdf\n";
+        assert!(!allows(robots, "AnyBot", "/docs/report.pdf"));
+        assert!(!allows(robots, "AnyBot", "/docs/report.pdf?download=1"));
+        assert!(allows(robots, "AnyBot", "/docs/report.html"));
+    }
+
+    #[test]
+    fn dollar_anchors_the_end() {
+        let robots = "User-agent: *\nDisallow: /*.php$\n";
+        assert!(!allows(robots, "AnyBot", "/index.php"));
+        assert!(allows(robots, "AnyBot", "/index.php?page=2"));
+        assert!(allows(robots, "AnyBot", "/index.php/extra"));
+    }
+
+    #[test]
+    fn falls_back_to_the_star_group() {
+        let robots = "User-agent: Googlebot\nDisallow: /private\n\nUser-agent: *\nDisallow: /\n";
+        let googlebot = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+        assert!(!allows(robots, googlebot, "/private/a"));
+        assert!(allows(robots, googlebot, "/public"));
+        assert!(!allows(robots, "Bingbot", "/public"));
+        assert!(allows(
+            "User-agent: Googlebot\nDisallow: /\n",
+            "Bingbot",
+            "/public"
+        ));
+    }
+
+    #[test]
+    fn groups_of_the_same_agent_are_merged() {
+        let robots = "User-agent: Bingbot\nDisallow: /a\n\nUser-agent: Googlebot\nDisallow: /c\n\n\
+                      User-agent: bingbot\nDisallow: /b\n";
+        assert!(!allows(robots, "Bingbot/2.0", "/a"));
+        assert!(!allows(robots, "Bingbot/2.0", "/b"));
+        assert!(allows(robots, "Bingbot/2.0", "/c"));
+    }
+
+    #[test]
+    fn consecutive_agents_share_a_group() {
+        let robots = "User-agent: Bingbot\nUser-agent: Googlebot\nDisallow: /a\n";
+        assert!(!allows(robots, "Bingbot", "/a"));
+        assert!(!allows(robots, "Googlebot", "/a"));
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        assert!(allows("User-agent: *\nDisallow:\n", "AnyBot", "/anything"));
+    }
+}
@@ -0,0 +1,242 @@
+use regex::RegexSet;
+use serde::Deserialize;
+use std::{error::Error, fs, path::Path};
+
+use crate::{log_entry::LogEntry, query_params::percent_decode};
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Url,
+    Ua,
+    Referrer,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Rule {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    pub pattern: String,
+    #[serde(default = "default_fields")]
+    pub fields: Vec<Field>,
+}
+
+fn default_fields() -> Vec<Field> {
+    vec![Field::Url]
+}
+
+#[derive(Deserialize)]
+struct RuleFile {
+    #[serde(rename = "rule", default)]
+    rules: Vec<Rule>,
+}
+
+/// Built-in signatures as (id, description, pattern, fields).
+const BUILTIN_RULES: &[(&str, &str, &str, &[Field])] = &[
+    (
+        "sqli-union",
+        "SQL injection with UNION SELECT",
+        r"(?i)union(\s|/\*.*?\*/)+(all(\s|/\*.*?\*/)+)?select",
+        &[Field::Url],
+    ),
+    (
+        "sqli-tautology",
+        "SQL injection with an always true condition",
+        r#"(?i)['"]\s*(or|and)\s+['"]?\w+['"]?\s*=\s*['"]?\w+"#,
+        &[Field::Url],
+    ),
+    (
+        "sqli-time",
+        "Time based blind SQL injection",
+        r"(?i)\b(sleep|benchmark|pg_sleep)\s*\(|waitfor\s+delay",
+        &[Field::Url, Field::Ua, Field::Referrer],
+    ),
+    (
+        "path-traversal",
+        "Directory traversal",
+        r"\.\.[/\\]",
+        &[Field::Url],
+    ),
+    (
+        "sensitive-file",
+        "System file access",
+        r"(?i)/etc/(passwd|shadow|hosts)|/proc/self/|win\.ini|boot\.ini",
+        &[Field::Url],
+    ),
+    (
+        "env-probe",
+        "Environment file probing",
+        r"(?i)/\.env\b",
+        &[Field::Url],
+    ),
+    (
+        "vcs-probe",
+        "Version control metadata probing",
+        r"(?i)/\.(git|svn|hg|bzr)(/|\s|$)",
+        &[Field::Url],
+    ),
+    (
+        "config-probe",
+        "Configuration and backup file probing",
+        r"(?i)/(wp-config\.php|\.htaccess|\.htpasswd|\.ds_store|config\.(php|ya?ml|json)\.(bak|old|save)|[\w.-]+\.(sql|bak)(\s|\?|$))",
+        &[Field::Url],
+    ),
+    (
+        "wordpress-scan",
+        "WordPress login and plugin scanning",
+        r"(?i)/(wp-login\.php|xmlrpc\.php|wp-admin/|wp-content/plugins/|wp-includes/)",
+        &[Field::Url],
+    ),
+    (
+        "phpmyadmin-scan",
+        "phpMyAdmin and database tool scanning",
+        r"(?i)/(phpmyadmin|pma|myadmin|mysqladmin|dbadmin|adminer)(/|\.php|\s|$)",
+        &[Field::Url],
+    ),
+    (
+        "log4shell",
+        "Log4Shell JNDI lookup",
+        r"(?i)\$\{(jndi|[^}]{0,20}\$\{(lower|upper|::-|env))",
+        &[Field::Url, Field::Ua, Field::Referrer],
+    ),
+    (
+        "shellshock",
+        "Shellshock function definition",
+        r"\(\)\s*\{\s*:;\s*\}",
+        &[Field::Url, Field::Ua, Field::Referrer],
+    ),
+    (
+        "command-injection",
+        "Shell command injection",
+        r"(?i)(;|\||&&|\$\(|`)\s*(cat|wget|curl|id|uname|whoami|nc|bash|sh|ping)\b",
+        &[Field::Url],
+    ),
+    (
+        "xss",
+        "Cross-site scripting",
+        r"(?i)<script|javascript:|\bon(error|load|mouseover)\s*=",
+        &[Field::Url, Field::Referrer],
+    ),
+    (
+        "cloud-metadata",
+        "Cloud metadata service access (SSRF)",
+        r"169\.254\.169\.254|metadata\.google\.internal",
+        &[Field::Url],
+    ),
+];
+
+/// Compiled signatures, matched against percent decoded request fields.
+#[derive(Debug)]
+pub struct RuleSet {
+    pub rules: Vec<Rule>,
+    // one set per field with the indexes of its rules
+    sets: Vec<(Field, RegexSet, Vec<usize>)>,
+}
+
+impl RuleSet {
+    pub fn builtin() -> Vec<Rule> {
+        BUILTIN_RULES
+            .iter()
+            .map(|(id, description, pattern, fields)| Rule {
+                id: id.to_string(),
+                description: description.to_string(),
+                pattern: pattern.to_string(),
+                fields: fields.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Reads `[[rule]]` tables from a TOML file.
+    pub fn load(path: &Path) -> Result<Vec<Rule>, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let file: RuleFile = toml::from_str(&content)
+            .map_err(|err| format!("invalid rule file {}: {err}", path.display()))?;
+        Ok(file.rules)
+    }
+
+    /// Later rules replace earlier ones with the same id.
+    pub fn new(rules: Vec<Rule>) -> Result<Self, Box<dyn Error>> {
+        let mut unique: Vec<Rule> = Vec::new();
+        for rule in rules {
+            match unique.iter_mut().find(|known| known.id == rule.id) {
+                Some(known) => *known = rule,
+                None => unique.push(rule),
+            }
+        }
+
+        let mut sets = Vec::new();
+        for field in [Field::Url, Field::Ua, Field::Referrer] {
+            let indexes: Vec<usize> = unique
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| rule.fields.contains(&field))
+                .map(|(index, _)| index)
+                .collect();
+            if indexes.is_empty() {
+                continue;
+            }
+            for &index in &indexes {
+                regex::Regex::new(&unique[index].pattern).map_err(|err| {
+                    format!("invalid pattern of rule {}: {err}", unique[index].id)
+                })?;
+            }
+            let set = RegexSet::new(indexes.iter().map(|&index| &unique[index].pattern))?;
+            sets.push((field, set, indexes));
+        }
+        Ok(Self {
+            rules: unique,
+            sets,
+        })
+    }
+
+    /// Ids of the rules matching any field of the entry, each at most once.
+    pub fn matches(&self, entry: &LogEntry) -> Vec<&str> {
+        let mut matched = vec![false; self.rules.len()];
+        for (field, set, indexes) in &self.sets {
+            let value = match field {
+                Field::Url => &entry.url,
+                Field::Ua => &entry.ua,
+                Field::Referrer => &entry.referrer,
+            };
+            let Some(value) = value else {
+                continue;
+            };
+            for text in Self::decoded(value) {
+                for index in set.matches(&text).iter() {
+                    matched[indexes[index]] = true;
+                }
+            }
+        }
+        self.rules
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| *matched)
+            .map(|(rule, _)| rule.id.as_str())
+            .collect()
+    }
+
+    pub fn description(&self, id: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.id == id)
+            .map(|rule| rule.description.as_str())
+    }
+
+    /// The raw value and up to two rounds of percent decoding, for double encoded payloads.
+    fn decoded(value: &str) -> Vec<String> {
+        let mut texts = vec![value.to_string()];
+        while texts.len() < 3 {
+            let last = &texts[texts.len() - 1];
+            if !last.contains('%') && !last.contains('+') {
+                break;
+            }
+            let decoded = percent_decode(last);
+            if &decoded == last {
+                break;
+            }
+            texts.push(decoded);
+        }
+        texts
+    }
+}
@@ -0,0 +1,109 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{ip_info::IpInfo, log_entry::LogEntry};
+
+/// An IP requesting many paths that do not exist.
+#[derive(Serialize, Debug, Clone)]
+pub struct ScanFinding {
+    pub not_found: usize,
+    pub not_found_ratio: f64,
+    pub missing_paths: usize,
+    pub top_paths: Vec<(String, usize)>,
+}
+
+impl ScanFinding {
+    /// Flags the IP when both its 404 ratio and distinct missing paths reach the thresholds.
+    pub fn new(ip_info: &IpInfo, min_ratio: f64, min_paths: usize, top: usize) -> Option<Self> {
+        let requests: usize = ip_info.status_map.values().sum();
+        let not_found: usize = ip_info.not_found_map.values().sum();
+        if requests == 0 {
+            return None;
+        }
+        let not_found_ratio = not_found as f64 / requests as f64;
+        if not_found_ratio < min_ratio || ip_info.not_found_map.len() < min_paths {
+            return None;
+        }
+
+        let mut top_paths: Vec<(String, usize)> = ip_info
+            .not_found_map
+            .iter()
+            .map(|(path, count)| (path.clone(), *count))
+            .collect();
+        top_paths.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_paths.truncate(top);
+
+        Some(Self {
+            not_found,
+            not_found_ratio,
+            missing_paths: ip_info.not_found_map.len(),
+            top_paths,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MissingPath {
+    pub count: usize,
+    pub ips: HashSet<String>,
+    pub referrers: HashMap<String, usize>,
+}
+
+/// 404 responses of all clients by URL, with the pages linking to them.
+#[derive(Debug, Default)]
+pub struct MissingPaths {
+    pub paths: HashMap<String, MissingPath>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MissingPathReport {
+    pub url: String,
+    pub count: usize,
+    pub ips: usize,
+    /// Referring pages, the likely location of a broken link
+    pub referrers: Vec<(String, usize)>,
+}
+
+impl MissingPaths {
+    pub fn add(&mut self, ip: &str, entry: &LogEntry) {
+        let (Some(404), Some(url)) = (entry.status, &entry.url) else {
+            return;
+        };
+        let path = match self.paths.get_mut(url) {
+            Some(path) => path,
+            None => self.paths.entry(url.clone()).or_default(),
+        };
+        path.count += 1;
+        if !path.ips.contains(ip) {
+            path.ips.insert(ip.to_string());
+        }
+        if let Some(referrer) = &entry.referrer
+            && referrer != "-"
+        {
+            *path.referrers.entry(referrer.clone()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn top(&self, limit: usize, referrers: usize) -> Vec<MissingPathReport> {
+        let mut vec: Vec<(&String, &MissingPath)> = self.paths.iter().collect();
+        vec.sort_unstable_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+        vec.into_iter()
+            .take(limit)
+            .map(|(url, path)| {
+                let mut top_referrers: Vec<(String, usize)> = path
+                    .referrers
+                    .iter()
+                    .map(|(referrer, count)| (referrer.clone(), *count))
+                    .collect();
+                top_referrers.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                top_referrers.truncate(referrers);
+                MissingPathReport {
+                    url: url.clone(),
+                    count: path.count,
+                    ips: path.ips.len(),
+                    referrers: top_referrers,
+                }
+            })
+            .collect()
+    }
+}
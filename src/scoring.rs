@@ -0,0 +1,423 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    crawler_verify::Verification,
+    grouping::AsnRange,
+    ip_info::IpInfo,
+    regularity::TimingStats,
+    ua_classifier::{self, UaCategory},
+};
+
+/// Values at which a signal reaches its full weight.
+const PEAK_RPM_SCALE: f64 = 60.0;
+const URL_BREADTH_SCALE: f64 = 200.0;
+const RULE_HITS_SCALE: f64 = 5.0;
+const UAS_PER_HOUR_SCALE: f64 = 10.0;
+
+/// Words in AS descriptions of hosting and cloud providers, matched as whole words.
+const DATACENTER_KEYWORDS: &[&str] = &[
+    "amazon",
+    "aws",
+    "google",
+    "microsoft",
+    "azure",
+    "digitalocean",
+    "ovh",
+    "hetzner",
+    "linode",
+    "akamai",
+    "vultr",
+    "choopa",
+    "alibaba",
+    "tencent",
+    "oracle",
+    "leaseweb",
+    "contabo",
+    "scaleway",
+    "online s.a.s",
+    "m247",
+    "hosting",
+    "hostinger",
+    "data center",
+    "datacenter",
+    "colocation",
+    "vps",
+];
+
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Signal {
+    /// Most requests within one minute
+    PeakRpm,
+    /// Share of responses with status 400 and above
+    ErrorRatio,
+    /// Share of 404 responses
+    NotFoundRatio,
+    /// Distinct URLs requested
+    UrlBreadth,
+    /// Requests matching attack rules
+    RuleHits,
+    /// Kind of client by user agent, spoofed crawlers score highest
+    UaCategory,
+    /// Address of a hosting or cloud provider, requires --asn-db
+    Datacenter,
+    /// Near-constant request intervals, only for IPs regular by --max-cv
+    Timing,
+    /// Most distinct user agents within one hour
+    UaRotation,
+    /// Listed on a --reputation list
+    Reputation,
+}
+
+impl Signal {
+    const ALL: [Signal; 10] = [
+        Signal::PeakRpm,
+        Signal::ErrorRatio,
+        Signal::NotFoundRatio,
+        Signal::UrlBreadth,
+        Signal::RuleHits,
+        Signal::UaCategory,
+        Signal::Datacenter,
+        Signal::Timing,
+        Signal::UaRotation,
+        Signal::Reputation,
+    ];
+
+    fn default_weight(&self) -> f64 {
+        match self {
+            Signal::RuleHits | Signal::Reputation => 2.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Parses `SIGNAL=WEIGHT` of --score-weight.
+pub fn parse_weight(value: &str) -> Result<(Signal, f64), String> {
+    let (signal, weight) = value
+        .split_once('=')
+        .ok_or_else(|| format!("'{value}' is not SIGNAL=WEIGHT"))?;
+    let signal = Signal::from_str(signal.trim(), true)?;
+    let weight = weight
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|weight| *weight >= 0.0)
+        .ok_or_else(|| format!("invalid weight '{weight}'"))?;
+    Ok((signal, weight))
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SignalScore {
+    pub signal: Signal,
+    /// Raw measurement, e.g. a ratio or a count
+    pub value: f64,
+    /// Measurement scaled to 0..1
+    pub normalized: f64,
+    pub weight: f64,
+    /// Points added to the score
+    pub points: f64,
+}
+
+/// Weighted suspicion score between 0 and 100, with the share of each signal.
+#[derive(Serialize, Debug, Clone)]
+pub struct Score {
+    pub total: f64,
+    pub signals: Vec<SignalScore>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scorer {
+    weights: Vec<(Signal, f64)>,
+}
+
+impl Scorer {
+    /// Signals in `unavailable` lack their input data, e.g. Datacenter without --asn-db,
+    /// and are left out so they do not dilute the others.
+    pub fn new(overrides: &[(Signal, f64)], unavailable: &[Signal]) -> Self {
+        let weights = Signal::ALL
+            .iter()
+            .filter(|signal| !unavailable.contains(signal))
+            .map(|signal| {
+                let weight = overrides
+                    .iter()
+                    .rev()
+                    .find(|(overridden, _)| overridden == signal)
+                    .map_or(signal.default_weight(), |(_, weight)| *weight);
+                (*signal, weight)
+            })
+            .collect();
+        Self { weights }
+    }
+
+    pub fn score(&self, ip_info: &IpInfo, asn: Option<&AsnRange>, max_cv: f64) -> Score {
+        let count = ip_info.count.max(1) as f64;
+        let requests: usize = ip_info.status_map.values().sum();
+        let ratio = |matching: usize| match requests {
+            0 => 0.0,
+            requests => matching as f64 / requests as f64,
+        };
+        let rule_hits: usize = ip_info.rule_hits.values().sum();
+        let timing = TimingStats::new(&ip_info.timestamps, max_cv);
+
+        let measure = |signal: &Signal| -> (f64, f64) {
+            match signal {
+                Signal::PeakRpm => {
+                    let peak = ip_info.peak_rpm() as f64;
+                    (peak, (peak / PEAK_RPM_SCALE).min(1.0))
+                }
+                Signal::ErrorRatio => {
+                    let ratio = ratio(ip_info.error_count());
+                    (ratio, ratio)
+                }
+                Signal::NotFoundRatio => {
+                    let ratio = ratio(ip_info.status_map.get(&404).copied().unwrap_or(0));
+                    (ratio, ratio)
+                }
+                Signal::UrlBreadth => {
+                    let urls = ip_info.url_map.len() as f64;
+                    (urls, (urls / URL_BREADTH_SCALE).min(1.0).min(urls / count))
+                }
+                Signal::RuleHits => (
+                    rule_hits as f64,
+                    (rule_hits as f64 / RULE_HITS_SCALE).min(1.0),
+                ),
+                Signal::UaCategory => {
+                    let value = Self::client_risk(ip_info);
+                    (value, value)
+                }
+                Signal::Datacenter => {
+                    let hosted = asn.is_some_and(|range| {
+                        let description = words(&range.description);
+                        DATACENTER_KEYWORDS.iter().any(|keyword| {
+                            let keyword = words(keyword);
+                            description
+                                .windows(keyword.len())
+                                .any(|window| window == keyword)
+                        })
+                    });
+                    let value = if hosted { 1.0 } else { 0.0 };
+                    (value, value)
+                }
+                // agrees with --regular-only, from half the weight at max_cv to full at 0
+                Signal::Timing => match &timing {
+                    Some(timing) if timing.regular => {
+                        (timing.cv, 1.0 - timing.cv / max_cv.max(f64::EPSILON) / 2.0)
+                    }
+                    Some(timing) => (timing.cv, 0.0),
+                    None => (0.0, 0.0),
+                },
+                Signal::UaRotation => {
+                    let uas = ip_info.ua_history.max_uas_per_hour() as f64;
+                    (uas, ((uas - 1.0) / (UAS_PER_HOUR_SCALE - 1.0)).clamp(0.0, 1.0))
+                }
+                Signal::Reputation => {
+                    let lists = ip_info.reputation.len() as f64;
+                    (lists, lists.min(1.0))
+                }
+            }
+        };
+
+        let total_weight: f64 = self.weights.iter().map(|(_, weight)| weight).sum();
+        let signals: Vec<SignalScore> = self
+            .weights
+            .iter()
+            .map(|(signal, weight)| {
+                let (value, normalized) = measure(signal);
+                let points = if total_weight > 0.0 {
+                    normalized * weight / total_weight * 100.0
+                } else {
+                    0.0
+                };
+                SignalScore {
+                    signal: *signal,
+                    value,
+                    normalized,
+                    weight: *weight,
+                    points,
+                }
+            })
+            .collect();
+        Score {
+            total: signals.iter().map(|signal| signal.points).sum(),
+            signals,
+        }
+    }
+
+    fn client_risk(ip_info: &IpInfo) -> f64 {
+        let Some((class, _)) = ua_classifier::dominant(&ip_info.ua_map) else {
+            return 1.0;
+        };
+        match (
+            class.category,
+            ip_info.crawler_check.as_ref().map(|check| check.status),
+        ) {
+            (_, Some(Verification::Verified)) => 0.0,
+            (_, Some(Verification::Spoofed)) => 1.0,
+            (UaCategory::Browser, _) => 0.0,
+            (UaCategory::SearchEngine, _) => 0.2,
+            (UaCategory::SeoCrawler, _) => 0.3,
+            (UaCategory::Bot, _) => 0.4,
+            (UaCategory::Unknown, _) => 0.5,
+            (UaCategory::HttpLibrary, _) => 0.7,
+            (UaCategory::Headless, _) => 0.8,
+            (UaCategory::Empty, _) => 1.0,
+        }
+    }
+}
+
+/// Lowercase alphanumeric words, "ONLINE S.A.S." becomes online, s, a, s.
+fn words(text: &str) -> Vec<String> {
+    text.split(|char: char| !char.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Scores every IP, `asn` looks up the AS of a key.
+pub fn score_ips<'a>(
+    ip_map: &mut HashMap<String, IpInfo>,
+    scorer: &Scorer,
+    max_cv: f64,
+    asn: impl Fn(&str, &IpInfo) -> Option<&'a AsnRange>,
+) {
+    for (key, ip_info) in ip_map.iter_mut() {
+        let score = scorer.score(ip_info, asn(key, ip_info), max_cv);
+        ip_info.score = Some(score);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Local};
+
+    fn ip_info(gaps: &[i64]) -> IpInfo {
+        let mut ip_info = IpInfo::new();
+        let mut second = 0;
+        for gap in std::iter::once(&0).chain(gaps) {
+            second += gap;
+            let timestamp = DateTime::from_timestamp(second, 0).unwrap();
+            ip_info.timestamps.push(timestamp.with_timezone(&Local));
+            ip_info.increment();
+        }
+        ip_info
+    }
+
+    fn signal(score: &Score, signal: Signal) -> Option<&SignalScore> {
+        score.signals.iter().find(|scored| scored.signal == signal)
+    }
+
+    fn asn(description: &str) -> AsnRange {
+        AsnRange {
+            start: 0,
+            end: 0,
+            asn: 1,
+            country: "US".to_string(),
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn unavailable_signals_are_left_out_of_the_total() {
+        // no user agent scores as an empty one, the only signal at full value
+        let ip_info = IpInfo::new();
+        let all = Scorer::new(&[], &[]).score(&ip_info, None, 0.3);
+        assert!((all.total - 100.0 / 12.0).abs() < 1e-9);
+
+        let unavailable = [Signal::Datacenter, Signal::RuleHits, Signal::Reputation];
+        let score = Scorer::new(&[], &unavailable).score(&ip_info, None, 0.3);
+        assert!(
+            unavailable
+                .iter()
+                .all(|dropped| signal(&score, *dropped).is_none())
+        );
+        assert_eq!(score.signals.len(), 7);
+        assert!((score.total - 100.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weights_are_overridden_and_zero_weights_score_nothing() {
+        let ip_info = IpInfo::new();
+        let overrides = [(Signal::UaCategory, 5.0), (Signal::UaCategory, 0.0)];
+        let score = Scorer::new(&overrides, &[]).score(&ip_info, None, 0.3);
+        assert_eq!(signal(&score, Signal::UaCategory).unwrap().weight, 0.0);
+        assert_eq!(score.total, 0.0);
+
+        let everything_off: Vec<(Signal, f64)> =
+            Signal::ALL.iter().map(|signal| (*signal, 0.0)).collect();
+        let score = Scorer::new(&everything_off, &[]).score(&ip_info, None, 0.3);
+        assert_eq!(score.total, 0.0);
+    }
+
+    #[test]
+    fn signals_are_normalized_to_their_scale() {
+        let mut ip_info = ip_info(&[0; 29]);
+        ip_info.status_map = HashMap::from([(200, 10), (404, 5), (500, 5)]);
+        ip_info.rule_hits = HashMap::from([("sqli".to_string(), 10)]);
+        ip_info.reputation = vec!["drop".to_string(), "tor".to_string()];
+        for url in 0..10 {
+            ip_info.url_map.insert(format!("/{url}"), 3);
+        }
+        let score = Scorer::new(&[], &[]).score(&ip_info, None, 0.3);
+        let normalized = |scored| signal(&score, scored).unwrap().normalized;
+
+        assert_eq!(signal(&score, Signal::PeakRpm).unwrap().value, 30.0);
+        assert_eq!(normalized(Signal::PeakRpm), 0.5);
+        assert_eq!(normalized(Signal::ErrorRatio), 0.5);
+        assert_eq!(normalized(Signal::NotFoundRatio), 0.25);
+        assert_eq!(normalized(Signal::UrlBreadth), 10.0 / URL_BREADTH_SCALE);
+        assert_eq!(normalized(Signal::RuleHits), 1.0);
+        assert_eq!(normalized(Signal::Reputation), 1.0);
+        assert!(score.total <= 100.0);
+    }
+
+    #[test]
+    fn timing_follows_max_cv() {
+        let constant = ip_info(&[60; 10]);
+        let score = Scorer::new(&[], &[]).score(&constant, None, 0.3);
+        assert_eq!(signal(&score, Signal::Timing).unwrap().normalized, 1.0);
+
+        // gaps of 10 and 30 seconds have a cv of 0.5
+        let alternating = ip_info(&[10, 30, 10, 30, 10, 30]);
+        let timing = |max_cv| {
+            let score = Scorer::new(&[], &[]).score(&alternating, None, max_cv);
+            signal(&score, Signal::Timing).unwrap().normalized
+        };
+        assert_eq!(timing(0.3), 0.0);
+        assert!((timing(0.5) - 0.5).abs() < 1e-9);
+        assert!((timing(1.0) - 0.75).abs() < 1e-9);
+
+        let too_few = ip_info(&[60; 2]);
+        let score = Scorer::new(&[], &[]).score(&too_few, None, 0.3);
+        assert_eq!(signal(&score, Signal::Timing).unwrap().normalized, 0.0);
+    }
+
+    #[test]
+    fn datacenter_keywords_match_whole_words() {
+        let hosted = |description| {
+            let score = Scorer::new(&[], &[]).score(&IpInfo::new(), Some(&asn(description)), 0.3);
+            signal(&score, Signal::Datacenter).unwrap().normalized
+        };
+        assert_eq!(hosted("AMAZON-02"), 1.0);
+        assert_eq!(hosted("ONLINE S.A.S."), 1.0);
+        assert_eq!(hosted("Hetzner Online GmbH"), 1.0);
+        assert_eq!(hosted("Some Data Center Ltd"), 1.0);
+        assert_eq!(hosted("Serverius Holding"), 0.0);
+        assert_eq!(hosted("Deutsche Telekom AG"), 0.0);
+        assert_eq!(hosted("Overhead Communications"), 0.0);
+    }
+
+    #[test]
+    fn parses_weights() {
+        assert_eq!(parse_weight("timing=2.5"), Ok((Signal::Timing, 2.5)));
+        assert_eq!(
+            parse_weight(" ua-rotation = 0 "),
+            Ok((Signal::UaRotation, 0.0))
+        );
+        assert!(parse_weight("timing").is_err());
+        assert!(parse_weight("timing=-1").is_err());
+        assert!(parse_weight("nonsense=1").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::log_entry::LogEntry;
+
+/// A single request of an IP, kept in order for session reconstruction.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    /// Source IP, a member of the key with --group-by
+    pub ip: String,
+    pub timestamp: DateTime<Local>,
+    pub url: String,
+    pub ua: String,
+}
+
+impl Hit {
+    pub fn from_entry(ip: &str, entry: &LogEntry) -> Option<Self> {
+        Some(Self {
+            ip: ip.to_string(),
+            timestamp: entry.timestamp?,
+            url: entry.url.clone().unwrap_or_default(),
+            ua: entry.ua.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Session<'a> {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub pages: usize,
+    pub entry_url: &'a str,
+    pub exit_url: &'a str,
+}
+
+/// Groups hits by source IP and user agent and splits each group whenever two
+/// consecutive requests are further apart than `gap`.
+pub fn reconstruct(hits: &[Hit], gap: TimeDelta) -> Vec<Session<'_>> {
+    let mut by_client: HashMap<(&str, &str), Vec<&Hit>> = HashMap::new();
+    for hit in hits {
+        by_client.entry((&hit.ip, &hit.ua)).or_default().push(hit);
+    }
+
+    let mut sessions = Vec::new();
+    for mut client_hits in by_client.into_values() {
+        client_hits.sort_by_key(|hit| hit.timestamp);
+
+        let mut current: Option<Session> = None;
+        for hit in client_hits {
+            match &mut current {
+                Some(session) if hit.timestamp - session.end <= gap => {
+                    session.end = hit.timestamp;
+                    session.pages += 1;
+                    session.exit_url = &hit.url;
+                }
+                _ => {
+                    if let Some(session) = current.take() {
+                        sessions.push(session);
+                    }
+                    current = Some(Session {
+                        start: hit.timestamp,
+                        end: hit.timestamp,
+                        pages: 1,
+                        entry_url: &hit.url,
+                        exit_url: &hit.url,
+                    });
+                }
+            }
+        }
+        sessions.extend(current);
+    }
+
+    sessions.sort_by_key(|session| session.start);
+    sessions
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub sessions: usize,
+    pub average_duration_secs: f64,
+    pub max_duration_secs: i64,
+    pub average_pages: f64,
+    pub entry_urls: Vec<(String, usize)>,
+    pub exit_urls: Vec<(String, usize)>,
+}
+
+impl SessionSummary {
+    pub fn new(hits: &[Hit], gap: TimeDelta, top: usize) -> Self {
+        let sessions = reconstruct(hits, gap);
+        let count = sessions.len().max(1) as f64;
+        let durations: Vec<i64> = sessions
+            .iter()
+            .map(|session| (session.end - session.start).num_seconds())
+            .collect();
+
+        let mut entry_urls: HashMap<&str, usize> = HashMap::new();
+        let mut exit_urls: HashMap<&str, usize> = HashMap::new();
+        for session in &sessions {
+            *entry_urls.entry(session.entry_url).or_insert(0) += 1;
+            *exit_urls.entry(session.exit_url).or_insert(0) += 1;
+        }
+
+        Self {
+            sessions: sessions.len(),
+            average_duration_secs: durations.iter().sum::<i64>() as f64 / count,
+            max_duration_secs: durations.iter().copied().max().unwrap_or(0),
+            average_pages: sessions.iter().map(|session| session.pages).sum::<usize>() as f64
+                / count,
+            entry_urls: top_urls(entry_urls, top),
+            exit_urls: top_urls(exit_urls, top),
+        }
+    }
+}
+
+fn top_urls(map: HashMap<&str, usize>, limit: usize) -> Vec<(String, usize)> {
+    let mut vec: Vec<(String, usize)> = map
+        .into_iter()
+        .map(|(url, count)| (url.to_string(), count))
+        .collect();
+    vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    vec.truncate(limit);
+    vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(ip: &str, minute: i64, url: &str, ua: &str) -> Hit {
+        Hit {
+            ip: ip.to_string(),
+            timestamp: DateTime::from_timestamp(minute * 60, 0)
+                .unwrap()
+                .with_timezone(&Local),
+            url: url.to_string(),
+            ua: ua.to_string(),
+        }
+    }
+
+    #[test]
+    fn splits_after_the_gap() {
+        let hits = vec![
+            hit("10.0.0.1", 0, "/a", "ua"),
+            hit("10.0.0.1", 10, "/b", "ua"),
+            hit("10.0.0.1", 50, "/c", "ua"),
+        ];
+        let sessions = reconstruct(&hits, TimeDelta::minutes(30));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(
+            (
+                sessions[0].pages,
+                sessions[0].entry_url,
+                sessions[0].exit_url
+            ),
+            (2, "/a", "/b")
+        );
+        assert_eq!((sessions[1].pages, sessions[1].entry_url), (1, "/c"));
+    }
+
+    #[test]
+    fn user_agents_are_separate_sessions() {
+        let hits = vec![
+            hit("10.0.0.1", 0, "/a", "one"),
+            hit("10.0.0.1", 1, "/b", "two"),
+        ];
+        assert_eq!(reconstruct(&hits, TimeDelta::minutes(30)).len(), 2);
+    }
+
+    #[test]
+    fn member_ips_sharing_a_user_agent_are_separate_sessions() {
+        let hits = vec![
+            hit("10.0.0.1", 0, "/a", "ua"),
+            hit("10.0.0.2", 1, "/b", "ua"),
+            hit("10.0.0.1", 2, "/c", "ua"),
+        ];
+        let sessions = reconstruct(&hits, TimeDelta::minutes(30));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!((sessions[0].pages, sessions[0].exit_url), (2, "/c"));
+        assert_eq!((sessions[1].pages, sessions[1].entry_url), (1, "/b"));
+    }
+}
@@ -0,0 +1,453 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// Space-Saving top-k counter. Keeps at most `capacity` keys, a new key
+/// replaces the smallest counter and inherits its count as error.
+#[derive(Debug)]
+pub struct SpaceSaving {
+    capacity: usize,
+    counters: HashMap<String, (u64, u64)>,
+    // (count, key) ordered so the minimum can be evicted
+    order: BTreeSet<(u64, String)>,
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counters: HashMap::new(),
+            order: BTreeSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, key: &str) {
+        if let Some((count, _)) = self.counters.get_mut(key) {
+            self.order.remove(&(*count, key.to_string()));
+            *count += 1;
+            self.order.insert((*count, key.to_string()));
+            return;
+        }
+
+        let (count, error) = if self.counters.len() < self.capacity {
+            (1, 0)
+        } else {
+            let (min_count, min_key) = self.order.pop_first().unwrap_or_default();
+            self.counters.remove(&min_key);
+            (min_count + 1, min_count)
+        };
+        self.counters.insert(key.to_string(), (count, error));
+        self.order.insert((count, key.to_string()));
+    }
+
+    /// Tracked keys with their estimated count and maximum overestimation,
+    /// highest estimate first.
+    pub fn top(&self, limit: usize) -> Vec<(&String, u64, u64)> {
+        self.order
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|(count, key)| (key, *count, self.counters[key].1))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Upper bound of the error of any estimate: every key seen more often
+    /// than this is guaranteed to be tracked.
+    pub fn error_bound(&self) -> u64 {
+        match self.counters.len() < self.capacity {
+            true => 0,
+            false => self.order.first().map(|(count, _)| *count).unwrap_or(0),
+        }
+    }
+
+}
+
+/// HyperLogLog distinct counter with 2^precision registers.
+#[derive(Debug)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(4, 18);
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn add(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = hash << self.precision | 1 << (self.precision - 1);
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&rank| 2f64.powi(-i32::from(rank)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for small cardinalities
+            return m * (m / zeros as f64).ln();
+        }
+        estimate
+    }
+
+    /// Relative standard error of the estimate.
+    pub fn relative_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// Merging t-digest for streaming quantile estimates.
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    count: f64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new(100.0)
+    }
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            count: 0.0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.buffer.push(value);
+        self.count += 1.0;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() >= self.compression as usize {
+            self.compress();
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count as u64
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all: Vec<Centroid> = self
+            .buffer
+            .drain(..)
+            .map(|value| Centroid {
+                mean: value,
+                weight: 1.0,
+            })
+            .chain(self.centroids.drain(..))
+            .collect();
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total = self.count;
+        let mut merged: Vec<Centroid> = Vec::with_capacity(self.compression as usize);
+        let mut cumulative = 0.0;
+        for centroid in all {
+            if let Some(last) = merged.last_mut() {
+                let q = (cumulative + (last.weight + centroid.weight) / 2.0) / total;
+                let limit = 4.0 * total * q * (1.0 - q) / self.compression;
+                if last.weight + centroid.weight <= limit.max(1.0) {
+                    let weight = last.weight + centroid.weight;
+                    last.mean += (centroid.mean - last.mean) * centroid.weight / weight;
+                    last.weight = weight;
+                    continue;
+                }
+                cumulative += last.weight;
+            }
+            merged.push(centroid);
+        }
+        self.centroids = merged;
+    }
+
+    /// Estimated value at quantile `q` (0.0 - 1.0), `None` when empty.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let compressed;
+        let centroids = match self.buffer.is_empty() {
+            true => &self.centroids,
+            false => {
+                let mut digest = self.clone();
+                digest.compress();
+                compressed = digest.centroids;
+                &compressed
+            }
+        };
+        if centroids.is_empty() {
+            return None;
+        }
+        if centroids.len() == 1 {
+            return Some(centroids[0].mean);
+        }
+
+        let target = q.clamp(0.0, 1.0) * self.count;
+        let mut cumulative = 0.0;
+        for (i, centroid) in centroids.iter().enumerate() {
+            let center = cumulative + centroid.weight / 2.0;
+            if target < center {
+                let (prev_mean, prev_center) = match i {
+                    0 => (self.min, 0.0),
+                    _ => {
+                        let prev = centroids[i - 1];
+                        (prev.mean, cumulative - prev.weight / 2.0)
+                    }
+                };
+                let ratio = (target - prev_center) / (center - prev_center);
+                return Some(prev_mean + (centroid.mean - prev_mean) * ratio);
+            }
+            cumulative += centroid.weight;
+        }
+
+        let last = centroids[centroids.len() - 1];
+        let last_center = self.count - last.weight / 2.0;
+        let ratio = (target - last_center) / (self.count - last_center);
+        Some(last.mean + (self.max - last.mean) * ratio.min(1.0))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub requests: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub total_ms: f64,
+}
+
+impl LatencySummary {
+    pub fn new(digest: &TDigest) -> Option<Self> {
+        Some(Self {
+            requests: digest.count(),
+            p50_ms: digest.quantile(0.5)?,
+            p95_ms: digest.quantile(0.95)?,
+            p99_ms: digest.quantile(0.99)?,
+            total_ms: digest.sum(),
+        })
+    }
+}
+
+/// Bounded memory replacement of the exact first pass.
+#[derive(Debug)]
+pub struct Sketches {
+    pub top_ips: SpaceSaving,
+    pub distinct_ips: HyperLogLog,
+    pub distinct_urls: HyperLogLog,
+    pub requests_per_minute: TDigest,
+    current_minute: Option<(i64, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SketchSummary {
+    pub tracked_ips: usize,
+    pub count_error_bound: u64,
+    pub distinct_ips: u64,
+    pub distinct_urls: u64,
+    pub distinct_relative_error: f64,
+    pub rpm_p50: Option<f64>,
+    pub rpm_p95: Option<f64>,
+    pub rpm_p99: Option<f64>,
+}
+
+impl Sketches {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            top_ips: SpaceSaving::new(capacity),
+            distinct_ips: HyperLogLog::new(14),
+            distinct_urls: HyperLogLog::new(14),
+            requests_per_minute: TDigest::default(),
+            current_minute: None,
+        }
+    }
+
+    /// Counts one request. Requests per minute assume the log is ordered by
+    /// time, in either direction.
+    pub fn observe(&mut self, key: &str, line: &str, timestamp: Option<DateTime<Local>>) {
+        self.top_ips.add(key);
+        self.distinct_ips.add(key);
+        if let Some(url) = line.split('"').nth(1) {
+            self.distinct_urls.add(url);
+        }
+
+        if let Some(timestamp) = timestamp {
+            let minute = timestamp.timestamp().div_euclid(60);
+            match &mut self.current_minute {
+                Some((current, count)) if *current == minute => *count += 1,
+                current => {
+                    if let Some((_, count)) = current.replace((minute, 1)) {
+                        self.requests_per_minute.add(count as f64);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn summary(&mut self) -> SketchSummary {
+        if let Some((_, count)) = self.current_minute.take() {
+            self.requests_per_minute.add(count as f64);
+        }
+        SketchSummary {
+            tracked_ips: self.top_ips.len(),
+            count_error_bound: self.top_ips.error_bound(),
+            distinct_ips: self.distinct_ips.estimate().round() as u64,
+            distinct_urls: self.distinct_urls.estimate().round() as u64,
+            distinct_relative_error: self.distinct_ips.relative_error(),
+            rpm_p50: self.requests_per_minute.quantile(0.5),
+            rpm_p95: self.requests_per_minute.quantile(0.95),
+            rpm_p99: self.requests_per_minute.quantile(0.99),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift so the tests need no random crate.
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    #[test]
+    fn space_saving_stays_within_error_bound() {
+        // Zipf-like: key i appears 2000 / i times
+        let mut stream: Vec<String> = Vec::new();
+        for i in 1..=500usize {
+            for _ in 0..2000 / i {
+                stream.push(format!("10.0.{}.{}", i / 256, i % 256));
+            }
+        }
+        let mut state = 42;
+        for i in (1..stream.len()).rev() {
+            stream.swap(i, xorshift(&mut state) as usize % (i + 1));
+        }
+
+        let mut exact: HashMap<&str, u64> = HashMap::new();
+        let mut sketch = SpaceSaving::new(50);
+        for key in &stream {
+            *exact.entry(key).or_insert(0) += 1;
+            sketch.add(key);
+        }
+
+        let total = stream.len() as u64;
+        let bound = sketch.error_bound();
+        assert!(bound <= total / 50, "bound {bound} exceeds N/k");
+        let tracked = sketch.top(50);
+        for (key, estimate, error) in &tracked {
+            let count = exact[key.as_str()];
+            assert!(*estimate >= count, "{key} underestimated");
+            assert!(estimate - error <= count, "{key} error too small");
+            assert!(*estimate <= count + bound, "{key} over the bound");
+        }
+        for (key, count) in &exact {
+            if *count > bound {
+                assert!(tracked.iter().any(|(tracked, _, _)| tracked == key), "{key} lost");
+            }
+        }
+    }
+
+    #[test]
+    fn hyperloglog_estimates_within_three_standard_errors() {
+        for distinct in [100usize, 10_000, 200_000] {
+            let mut sketch = HyperLogLog::new(14);
+            for i in 0..distinct {
+                sketch.add(&i.to_string());
+                // duplicates must not change the estimate
+                sketch.add(&i.to_string());
+            }
+            let error = (sketch.estimate() - distinct as f64).abs() / distinct as f64;
+            assert!(
+                error < 3.0 * sketch.relative_error(),
+                "{distinct}: relative error {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn tdigest_quantiles_on_uniform_values() {
+        let mut digest = TDigest::default();
+        let mut state = 7;
+        let mut values: Vec<f64> = (0..100_000)
+            .map(|_| (xorshift(&mut state) % 10_000) as f64)
+            .collect();
+        for value in &values {
+            digest.add(*value);
+        }
+        values.sort_by(f64::total_cmp);
+
+        for q in [0.01, 0.5, 0.95, 0.99] {
+            let estimate = digest.quantile(q).unwrap();
+            // compare by rank: the estimate must sit within 1% of the requested quantile
+            let rank = values.partition_point(|value| *value < estimate) as f64 / values.len() as f64;
+            assert!((rank - q).abs() < 0.01, "q{q}: estimate {estimate} at rank {rank}");
+        }
+        assert_eq!(digest.count(), 100_000);
+    }
+
+    #[test]
+    fn tdigest_tail_on_skewed_values() {
+        // 99% fast requests and 1% slow ones, p99.5 has to land among the slow ones
+        let mut digest = TDigest::default();
+        for i in 0..99_000 {
+            digest.add(10.0 + (i % 10) as f64);
+        }
+        for i in 0..1_000 {
+            digest.add(1000.0 + i as f64);
+        }
+        assert!(digest.quantile(0.5).unwrap() < 20.0);
+        assert!(digest.quantile(0.995).unwrap() > 1000.0);
+        assert!(digest.quantile(1.0).unwrap() <= 1999.0);
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Local, TimeDelta, TimeZone};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Timeline {
+    pub start: DateTime<Local>,
+    pub interval_minutes: u32,
+    pub buckets: Vec<usize>,
+}
+
+impl Timeline {
+    /// Creates empty buckets covering `start..=end`, aligned to the interval.
+    pub fn new(start: DateTime<Local>, end: DateTime<Local>, interval_minutes: u32) -> Self {
+        let interval_secs = i64::from(interval_minutes.max(1)) * 60;
+        let aligned = start.timestamp() - start.timestamp().rem_euclid(interval_secs);
+        let start = Local.timestamp_opt(aligned, 0).single().unwrap_or(start);
+        let span = (end - start).num_seconds().max(0);
+
+        Self {
+            start,
+            interval_minutes: interval_minutes.max(1),
+            buckets: vec![0; (span / interval_secs) as usize + 1],
+        }
+    }
+
+    pub fn from_timestamps(
+        timestamps: &[DateTime<Local>],
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        interval_minutes: u32,
+    ) -> Self {
+        let mut timeline = Self::new(start, end, interval_minutes);
+        for timestamp in timestamps {
+            timeline.add(*timestamp);
+        }
+        timeline
+    }
+
+    pub fn interval(&self) -> TimeDelta {
+        TimeDelta::minutes(i64::from(self.interval_minutes))
+    }
+
+    pub fn bucket_index(&self, timestamp: DateTime<Local>) -> Option<usize> {
+        if timestamp < self.start {
+            return None;
+        }
+        let index =
+            ((timestamp - self.start).num_seconds() / self.interval().num_seconds()) as usize;
+        (index < self.buckets.len()).then_some(index)
+    }
+
+    pub fn add(&mut self, timestamp: DateTime<Local>) {
+        if let Some(index) = self.bucket_index(timestamp) {
+            self.buckets[index] += 1;
+        }
+    }
+
+    /// Returns the earliest and latest timestamp across all given timestamp lists.
+    pub fn window<'a>(
+        timestamps: impl Iterator<Item = &'a Vec<DateTime<Local>>>,
+    ) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        timestamps
+            .flatten()
+            .fold(None, |window, &timestamp| match window {
+                None => Some((timestamp, timestamp)),
+                Some((min, max)) => Some((min.min(timestamp), max.max(timestamp))),
+            })
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrafficBucket {
+    count: usize,
+    ips: HashMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrafficRow {
+    pub start: DateTime<Local>,
+    pub count: usize,
+    pub peak: bool,
+    pub top_ips: Vec<(String, usize)>,
+}
+
+/// Request counts of all clients, bucketed by interval.
+#[derive(Debug)]
+pub struct TrafficTimeline {
+    interval_minutes: u32,
+    buckets: BTreeMap<i64, TrafficBucket>,
+}
+
+impl TrafficTimeline {
+    pub fn new(interval_minutes: u32) -> Self {
+        Self {
+            interval_minutes: interval_minutes.max(1),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    pub fn interval_minutes(&self) -> u32 {
+        self.interval_minutes
+    }
+
+    pub fn add(&mut self, ip: &str, timestamp: DateTime<Local>) {
+        let interval_secs = i64::from(self.interval_minutes) * 60;
+        let key = timestamp.timestamp() - timestamp.timestamp().rem_euclid(interval_secs);
+        let bucket = self.buckets.entry(key).or_default();
+        bucket.count += 1;
+        *bucket.ips.entry(ip.to_string()).or_insert(0) += 1;
+    }
+
+    /// Returns one row per interval between the first and last bucket, with the
+    /// `top` contributing IPs. Buckets above mean + 2 standard deviations and
+    /// the highest bucket are marked as peaks.
+    pub fn rows(&self, top: usize) -> Vec<TrafficRow> {
+        let (Some(&first), Some(&last)) = (self.buckets.keys().next(), self.buckets.keys().last())
+        else {
+            return Vec::new();
+        };
+        let interval_secs = i64::from(self.interval_minutes) * 60;
+        let slots = ((last - first) / interval_secs + 1) as f64;
+
+        let total: usize = self.buckets.values().map(|bucket| bucket.count).sum();
+        let mean = total as f64 / slots;
+        let variance = self
+            .buckets
+            .values()
+            .map(|bucket| (bucket.count as f64 - mean).powi(2))
+            .sum::<f64>()
+            + (slots - self.buckets.len() as f64) * mean.powi(2);
+        let threshold = mean + 2.0 * (variance / slots).sqrt();
+        let max = self
+            .buckets
+            .values()
+            .map(|bucket| bucket.count)
+            .max()
+            .unwrap_or(0);
+
+        (first..=last)
+            .step_by(interval_secs as usize)
+            .map(|key| {
+                let start = Local.timestamp_opt(key, 0).single().unwrap_or_default();
+                match self.buckets.get(&key) {
+                    Some(bucket) => {
+                        let mut top_ips: Vec<(String, usize)> = bucket
+                            .ips
+                            .iter()
+                            .map(|(ip, count)| (ip.clone(), *count))
+                            .collect();
+                        top_ips.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                        top_ips.truncate(top);
+                        TrafficRow {
+                            start,
+                            count: bucket.count,
+                            peak: bucket.count as f64 > threshold
+                                || (bucket.count == max && max as f64 > mean),
+                            top_ips,
+                        }
+                    }
+                    None => TrafficRow {
+                        start,
+                        count: 0,
+                        peak: false,
+                        top_ips: Vec::new(),
+                    },
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Local};
+use regex::RegexSet;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// First request of an IP to a trap path.
+#[derive(Serialize, Debug, Clone)]
+pub struct TrapHit {
+    pub path: String,
+    pub timestamp: Option<DateTime<Local>>,
+    /// Requests to any trap path
+    pub hits: usize,
+}
+
+/// Hidden URLs no human requests, checked during the first pass so trapped IPs
+/// can be kept regardless of --max-ips.
+#[derive(Debug)]
+pub struct TrapPaths {
+    patterns: RegexSet,
+    /// Trap hits by IP key
+    pub trapped: HashMap<String, TrapHit>,
+}
+
+impl TrapPaths {
+    /// `*` in a pattern matches any characters. Patterns without `?` ignore the query string.
+    pub fn new(patterns: &[String]) -> Result<Self, regex::Error> {
+        let patterns = RegexSet::new(patterns.iter().map(|pattern| {
+            let pattern = regex::escape(pattern).replace(r"\*", ".*");
+            format!("^{pattern}$")
+        }))?;
+        Ok(Self {
+            patterns,
+            trapped: HashMap::new(),
+        })
+    }
+
+    fn is_trap(&self, request: &str) -> Option<String> {
+        let target = request.split(' ').nth(1)?;
+        let path = target.split(['?', '#']).next().unwrap_or_default();
+        if self.patterns.is_match(path) {
+            return Some(path.to_string());
+        }
+        self.patterns.is_match(target).then(|| target.to_string())
+    }
+
+    /// Keeps the earliest hit, the first pass may read the log in reverse.
+    pub fn check(&mut self, key: &str, request: &str, timestamp: Option<DateTime<Local>>) {
+        let Some(path) = self.is_trap(request) else {
+            return;
+        };
+        match self.trapped.get_mut(key) {
+            Some(hit) => {
+                hit.hits += 1;
+                if timestamp.is_some() && (hit.timestamp.is_none() || timestamp < hit.timestamp) {
+                    hit.path = path;
+                    hit.timestamp = timestamp;
+                }
+            }
+            None => {
+                self.trapped.insert(
+                    key.to_string(),
+                    TrapHit {
+                        path,
+                        timestamp,
+                        hits: 1,
+                    },
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{collections::HashMap, fmt};
+
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum UaCategory {
+    Browser,
+    /// Crawlers of search engines, e.g. Googlebot or bingbot
+    SearchEngine,
+    /// SEO and marketing crawlers, e.g. AhrefsBot or SemrushBot
+    SeoCrawler,
+    /// Other self-declared bots, crawlers and monitors
+    Bot,
+    /// Scripts and tools, e.g. curl, python-requests or Go-http-client
+    HttpLibrary,
+    /// Automated browsers, e.g. HeadlessChrome or PhantomJS
+    Headless,
+    /// Missing, too short or unreadable user agents
+    Empty,
+    Unknown,
+}
+
+impl fmt::Display for UaCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Browser => "browser",
+            Self::SearchEngine => "search engine",
+            Self::SeoCrawler => "SEO crawler",
+            Self::Bot => "bot",
+            Self::HttpLibrary => "HTTP library",
+            Self::Headless => "headless browser",
+            Self::Empty => "empty",
+            Self::Unknown => "unknown",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Device {
+    Desktop,
+    Mobile,
+    Tablet,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct UaClass {
+    pub category: UaCategory,
+    pub family: String,
+    pub os: Option<&'static str>,
+    pub device: Option<Device>,
+}
+
+/// Lowercase tokens of known clients, checked in order.
+const KNOWN_CLIENTS: &[(&str, &str, UaCategory)] = &[
+    ("headlesschrome", "HeadlessChrome", UaCategory::Headless),
+    ("phantomjs", "PhantomJS", UaCategory::Headless),
+    ("puppeteer", "Puppeteer", UaCategory::Headless),
+    ("playwright", "Playwright", UaCategory::Headless),
+    ("selenium", "Selenium", UaCategory::Headless),
+    ("slimerjs", "SlimerJS", UaCategory::Headless),
+    ("googlebot", "Googlebot", UaCategory::SearchEngine),
+    (
+        "google-inspectiontool",
+        "Googlebot",
+        UaCategory::SearchEngine,
+    ),
+    ("adsbot-google", "Googlebot", UaCategory::SearchEngine),
+    ("bingbot", "Bingbot", UaCategory::SearchEngine),
+    ("bingpreview", "Bingbot", UaCategory::SearchEngine),
+    ("yandexbot", "YandexBot", UaCategory::SearchEngine),
+    ("yandex.com/bots", "YandexBot", UaCategory::SearchEngine),
+    ("applebot", "Applebot", UaCategory::SearchEngine),
+    ("duckduckbot", "DuckDuckBot", UaCategory::SearchEngine),
+    ("baiduspider", "Baiduspider", UaCategory::SearchEngine),
+    ("slurp", "Yahoo! Slurp", UaCategory::SearchEngine),
+    ("sogou", "Sogou", UaCategory::SearchEngine),
+    ("seznambot", "SeznamBot", UaCategory::SearchEngine),
+    ("petalbot", "PetalBot", UaCategory::SearchEngine),
+    ("ahrefsbot", "AhrefsBot", UaCategory::SeoCrawler),
+    ("semrushbot", "SemrushBot", UaCategory::SeoCrawler),
+    ("mj12bot", "MJ12bot", UaCategory::SeoCrawler),
+    ("dotbot", "DotBot", UaCategory::SeoCrawler),
+    ("rogerbot", "rogerbot", UaCategory::SeoCrawler),
+    ("blexbot", "BLEXBot", UaCategory::SeoCrawler),
+    ("dataforseobot", "DataForSeoBot", UaCategory::SeoCrawler),
+    ("serpstatbot", "SerpstatBot", UaCategory::SeoCrawler),
+    ("screaming frog", "Screaming Frog", UaCategory::SeoCrawler),
+    ("megaindex", "MegaIndex", UaCategory::SeoCrawler),
+    ("barkrowler", "Barkrowler", UaCategory::SeoCrawler),
+    ("curl/", "curl", UaCategory::HttpLibrary),
+    ("wget/", "Wget", UaCategory::HttpLibrary),
+    (
+        "python-requests",
+        "python-requests",
+        UaCategory::HttpLibrary,
+    ),
+    ("python-urllib", "Python-urllib", UaCategory::HttpLibrary),
+    ("python-httpx", "httpx", UaCategory::HttpLibrary),
+    ("aiohttp", "aiohttp", UaCategory::HttpLibrary),
+    ("scrapy", "Scrapy", UaCategory::HttpLibrary),
+    ("go-http-client", "Go-http-client", UaCategory::HttpLibrary),
+    ("okhttp", "okhttp", UaCategory::HttpLibrary),
+    (
+        "apache-httpclient",
+        "Apache-HttpClient",
+        UaCategory::HttpLibrary,
+    ),
+    ("java/", "Java", UaCategory::HttpLibrary),
+    ("libwww-perl", "libwww-perl", UaCategory::HttpLibrary),
+    ("node-fetch", "node-fetch", UaCategory::HttpLibrary),
+    ("axios", "axios", UaCategory::HttpLibrary),
+    ("guzzlehttp", "Guzzle", UaCategory::HttpLibrary),
+    ("postmanruntime", "Postman", UaCategory::HttpLibrary),
+    ("httpie", "HTTPie", UaCategory::HttpLibrary),
+    ("reqwest", "reqwest", UaCategory::HttpLibrary),
+    ("ruby/", "Ruby", UaCategory::HttpLibrary),
+    ("php/", "PHP", UaCategory::HttpLibrary),
+];
+
+const BOT_MARKERS: &[&str] = &["bot", "crawl", "spider", "monitor", "scan", "fetch"];
+
+/// Browser families by the product token that identifies them, most specific first.
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("EdgA/", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("YaBrowser/", "Yandex Browser"),
+    ("Vivaldi/", "Vivaldi"),
+    ("Firefox/", "Firefox"),
+    ("FxiOS/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Version/", "Safari"),
+    ("MSIE ", "Internet Explorer"),
+    ("Trident/", "Internet Explorer"),
+];
+
+const OPERATING_SYSTEMS: &[(&str, &str)] = &[
+    ("Windows", "Windows"),
+    ("iPhone", "iOS"),
+    ("iPad", "iOS"),
+    ("iPod", "iOS"),
+    ("Android", "Android"),
+    ("CrOS", "ChromeOS"),
+    ("Mac OS X", "macOS"),
+    ("Macintosh", "macOS"),
+    ("Linux", "Linux"),
+];
+
+/// Whether `needle` occurs in `haystack` at the start of a token, so `ruby/`
+/// does not match inside another product name.
+fn contains_token(haystack: &str, needle: &str) -> bool {
+    haystack.match_indices(needle).any(|(index, _)| {
+        haystack[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|char| !char.is_alphanumeric())
+    })
+}
+
+pub fn classify(ua: &str) -> UaClass {
+    let ua = ua.trim();
+    let class = |category, family: &str| UaClass {
+        category,
+        family: family.to_string(),
+        os: None,
+        device: None,
+    };
+
+    if ua.is_empty() || ua == "-" {
+        return class(UaCategory::Empty, "empty");
+    }
+    if ua.len() < 5
+        || !ua.chars().any(char::is_alphabetic)
+        || ua
+            .chars()
+            .any(|char| char.is_control() || char == '\u{fffd}')
+    {
+        return class(UaCategory::Empty, "garbage");
+    }
+
+    let lowercase = ua.to_lowercase();
+    if let Some((_, family, category)) = KNOWN_CLIENTS
+        .iter()
+        .find(|(needle, _, _)| contains_token(&lowercase, needle))
+    {
+        return class(*category, family);
+    }
+    if BOT_MARKERS.iter().any(|marker| lowercase.contains(marker)) {
+        let family = ua
+            .split(['/', ' ', ';', '('])
+            .find(|token| !token.is_empty());
+        return class(UaCategory::Bot, family.unwrap_or(ua));
+    }
+    if !ua.starts_with("Mozilla/") && !ua.starts_with("Opera/") {
+        return class(UaCategory::Unknown, "unknown");
+    }
+
+    let family = BROWSERS
+        .iter()
+        .find(|(token, _)| ua.contains(token))
+        .map_or("other", |(_, family)| family);
+    let os = OPERATING_SYSTEMS
+        .iter()
+        .find(|(token, _)| ua.contains(token))
+        .map(|(_, os)| *os);
+    let device = if ua.contains("iPad") || ua.contains("Tablet") {
+        Device::Tablet
+    } else if ua.contains("Mobile") || ua.contains("iPhone") {
+        Device::Mobile
+    } else if ua.contains("Android") {
+        Device::Tablet
+    } else {
+        Device::Desktop
+    };
+    UaClass {
+        category: UaCategory::Browser,
+        family: family.to_string(),
+        os,
+        device: Some(device),
+    }
+}
+
+/// Class of the user agent sending most of the requests, with its share of requests.
+pub fn dominant(ua_map: &HashMap<String, usize>) -> Option<(UaClass, f64)> {
+    let total: usize = ua_map.values().sum();
+    let mut shares: Vec<(UaClass, usize)> = Vec::new();
+    for (ua, &count) in ua_map {
+        let class = classify(ua);
+        match shares.iter_mut().find(|(known, _)| *known == class) {
+            Some((_, known_count)) => *known_count += count,
+            None => shares.push((class, count)),
+        }
+    }
+    shares
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.family.cmp(&a.0.family)))
+        .map(|(class, count)| (class, count as f64 / total as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(ua: &str) -> UaCategory {
+        classify(ua).category
+    }
+
+    #[test]
+    fn browsers_with_os_and_device() {
+        let chrome = classify(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+             Chrome/126.0.0.0 Safari/537.36",
+        );
+        assert_eq!(chrome.category, UaCategory::Browser);
+        assert_eq!(chrome.family, "Chrome");
+        assert_eq!(
+            (chrome.os, chrome.device),
+            (Some("Windows"), Some(Device::Desktop))
+        );
+
+        let edge = classify(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+             Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0",
+        );
+        assert_eq!(edge.family, "Edge");
+
+        let iphone = classify(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1",
+        );
+        assert_eq!(iphone.family, "Safari");
+        assert_eq!(
+            (iphone.os, iphone.device),
+            (Some("iOS"), Some(Device::Mobile))
+        );
+
+        let android_tablet = classify(
+            "Mozilla/5.0 (Linux; Android 14; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) \
+             Chrome/126.0.0.0 Safari/537.36",
+        );
+        assert_eq!(
+            (android_tablet.os, android_tablet.device),
+            (Some("Android"), Some(Device::Tablet))
+        );
+    }
+
+    #[test]
+    fn crawlers() {
+        let googlebot =
+            classify("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)");
+        assert_eq!(googlebot.category, UaCategory::SearchEngine);
+        assert_eq!(googlebot.family, "Googlebot");
+        assert_eq!(
+            category("Mozilla/5.0 (compatible; YandexBot/3.0; +http://yandex.com/bots)"),
+            UaCategory::SearchEngine
+        );
+        assert_eq!(
+            category("Mozilla/5.0 (compatible; AhrefsBot/7.0; +http://ahrefs.com/robot/)"),
+            UaCategory::SeoCrawler
+        );
+        let bot = classify("UptimeRobot/2.0 (http://www.uptimerobot.com/)");
+        assert_eq!(
+            (bot.category, bot.family.as_str()),
+            (UaCategory::Bot, "UptimeRobot")
+        );
+    }
+
+    #[test]
+    fn yandex_apps_are_browsers() {
+        let ua = "Mozilla/5.0 (Linux; Android 13) AppleWebKit/537.36 (KHTML, like Gecko) \
+                  Chrome/120.0.0.0 YaBrowser/24.1.0 Mobile Safari/537.36";
+        let class = classify(ua);
+        assert_eq!(class.category, UaCategory::Browser);
+        assert_eq!(class.family, "Yandex Browser");
+    }
+
+    #[test]
+    fn libraries_and_headless_browsers() {
+        assert_eq!(classify("curl/8.4.0").family, "curl");
+        assert_eq!(category("python-requests/2.31.0"), UaCategory::HttpLibrary);
+        assert_eq!(category("Go-http-client/1.1"), UaCategory::HttpLibrary);
+        assert_eq!(category("Ruby/3.2"), UaCategory::HttpLibrary);
+        assert_eq!(
+            category(
+                "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) \
+                 HeadlessChrome/120.0.0.0 Safari/537.36"
+            ),
+            UaCategory::Headless
+        );
+    }
+
+    #[test]
+    fn tokens_match_only_at_word_starts() {
+        assert!(contains_token("ruby/3.2", "ruby/"));
+        assert!(contains_token("faraday v2; ruby/3.2", "ruby/"));
+        assert!(!contains_token("someruby/1.0", "ruby/"));
+        assert_eq!(
+            category("Mozilla/5.0 (compatible; MyRuby/1.0)"),
+            UaCategory::Browser
+        );
+    }
+
+    #[test]
+    fn empty_and_garbage() {
+        assert_eq!(classify("-").family, "empty");
+        assert_eq!(classify("  ").family, "empty");
+        assert_eq!(classify("abc").family, "garbage");
+        assert_eq!(classify("1234567").family, "garbage");
+        assert_eq!(classify("Mozilla\u{fffd}5.0").family, "garbage");
+        assert_eq!(category("SomethingElse 1.0"), UaCategory::Unknown);
+    }
+
+    #[test]
+    fn dominant_class_sums_user_agents_of_one_class() {
+        let ua_map = HashMap::from([
+            ("curl/8.0".to_string(), 3),
+            ("curl/7.0".to_string(), 3),
+            ("python-requests/2.31.0".to_string(), 4),
+        ]);
+        let (class, share) = dominant(&ua_map).unwrap();
+        assert_eq!(class.family, "curl");
+        assert_eq!(share, 0.6);
+        assert!(dominant(&HashMap::new()).is_none());
+    }
+}
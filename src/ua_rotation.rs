@@ -0,0 +1,84 @@
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Idle time that ends a session when counting user agent switches, unless --session-gap is set.
+pub const DEFAULT_SESSION_GAP: TimeDelta = TimeDelta::minutes(30);
+
+/// User agents of one IP over time, fed in log order.
+#[derive(Debug, Clone, Default)]
+pub struct UaHistory {
+    hours: HashMap<i64, HashSet<String>>,
+    last: Option<(String, DateTime<Local>)>,
+    sessions: usize,
+    switches: usize,
+    session_switches: usize,
+    max_session_switches: usize,
+}
+
+impl UaHistory {
+    /// Works in either direction, the full pass reads the log in reverse with --filter-hours.
+    pub fn add(&mut self, ua: &str, timestamp: DateTime<Local>, session_gap: TimeDelta) {
+        let hour = timestamp.timestamp().div_euclid(3600);
+        let uas = self.hours.entry(hour).or_default();
+        if !uas.contains(ua) {
+            uas.insert(ua.to_string());
+        }
+
+        match &mut self.last {
+            Some((last_ua, last_timestamp)) => {
+                if (timestamp - *last_timestamp).abs() > session_gap {
+                    self.sessions += 1;
+                    self.session_switches = 0;
+                } else if last_ua != ua {
+                    self.switches += 1;
+                    self.session_switches += 1;
+                    self.max_session_switches =
+                        self.max_session_switches.max(self.session_switches);
+                }
+                if last_ua != ua {
+                    *last_ua = ua.to_string();
+                }
+                *last_timestamp = timestamp;
+            }
+            None => {
+                self.sessions = 1;
+                self.last = Some((ua.to_string(), timestamp));
+            }
+        }
+    }
+
+    pub fn max_uas_per_hour(&self) -> usize {
+        self.hours.values().map(HashSet::len).max().unwrap_or(0)
+    }
+}
+
+/// How often an IP changes its user agent, scrapers rotate fingerprints.
+#[derive(Serialize, Debug, Clone)]
+pub struct UaRotation {
+    pub distinct_uas: usize,
+    pub max_uas_per_hour: usize,
+    /// Average over the hours the IP was active in
+    pub average_uas_per_hour: f64,
+    /// Changes of the user agent between consecutive requests within a session
+    pub switches: usize,
+    pub sessions: usize,
+    pub max_switches_per_session: usize,
+}
+
+impl UaRotation {
+    pub fn new(history: &UaHistory, distinct_uas: usize) -> Option<Self> {
+        if history.hours.is_empty() {
+            return None;
+        }
+        let active_uas: usize = history.hours.values().map(HashSet::len).sum();
+        Some(Self {
+            distinct_uas,
+            max_uas_per_hour: history.max_uas_per_hour(),
+            average_uas_per_hour: active_uas as f64 / history.hours.len() as f64,
+            switches: history.switches,
+            sessions: history.sessions,
+            max_switches_per_session: history.max_session_switches,
+        })
+    }
+}
@@ -0,0 +1,205 @@
+use regex::Regex;
+use std::error::Error;
+
+/// Rewrites request URLs into route templates so `/product/123` and
+/// `/product/124` are counted as `/product/{id}`.
+#[derive(Debug)]
+pub struct UrlNormalizer {
+    strip_query: bool,
+    collapse_ids: bool,
+    rules: Vec<(Regex, String)>,
+}
+
+impl UrlNormalizer {
+    /// Rules are `PATTERN=>REPLACEMENT` pairs applied in order to the URL path,
+    /// the replacement may reference capture groups as `$1` or `${name}`.
+    pub fn new(
+        strip_query: bool,
+        collapse_ids: bool,
+        rules: &[String],
+    ) -> Result<Self, Box<dyn Error>> {
+        let rules = rules
+            .iter()
+            .map(|rule| match rule.split_once("=>") {
+                Some((pattern, replacement)) => Ok((Regex::new(pattern)?, replacement.to_string())),
+                None => Err(format!(
+                    "invalid rewrite rule '{rule}', expected PATTERN=>REPLACEMENT"
+                )
+                .into()),
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+        Ok(Self {
+            strip_query,
+            collapse_ids,
+            rules,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.strip_query || self.collapse_ids || !self.rules.is_empty()
+    }
+
+    /// Normalizes either a bare URL or a request line (`GET /path HTTP/1.1`).
+    pub fn normalize(&self, url: &str) -> String {
+        let mut parts = url.splitn(3, ' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), protocol) => {
+                let mut line = format!("{method} {}", self.normalize_target(target));
+                if let Some(protocol) = protocol {
+                    line.push(' ');
+                    line.push_str(protocol);
+                }
+                line
+            }
+            _ => self.normalize_target(url),
+        }
+    }
+
+    fn normalize_target(&self, target: &str) -> String {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (target, None),
+        };
+
+        let mut path = match self.collapse_ids {
+            true => path
+                .split('/')
+                .map(|segment| placeholder(segment).unwrap_or(segment))
+                .collect::<Vec<_>>()
+                .join("/"),
+            false => path.to_string(),
+        };
+        for (pattern, replacement) in &self.rules {
+            path = pattern
+                .replace_all(&path, replacement.as_str())
+                .into_owned();
+        }
+
+        match query {
+            Some(query) if !self.strip_query => format!("{path}?{query}"),
+            _ => path,
+        }
+    }
+}
+
+fn placeholder(segment: &str) -> Option<&'static str> {
+    if segment.is_empty() {
+        return None;
+    }
+    if segment.chars().all(|c| c.is_ascii_digit()) {
+        return Some("{id}");
+    }
+    if is_uuid(segment) {
+        return Some("{uuid}");
+    }
+    // md5, sha1, sha256 and similar hex digests or object ids
+    if segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some("{hash}");
+    }
+    None
+}
+
+fn is_uuid(segment: &str) -> bool {
+    let groups: Vec<&str> = segment.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalizer(strip_query: bool, collapse_ids: bool, rules: &[&str]) -> UrlNormalizer {
+        let rules: Vec<String> = rules.iter().map(|rule| rule.to_string()).collect();
+        UrlNormalizer::new(strip_query, collapse_ids, &rules).unwrap()
+    }
+
+    #[test]
+    fn collapses_ids_uuids_and_hashes() {
+        let normalizer = normalizer(false, true, &[]);
+        assert_eq!(normalizer.normalize("/product/123"), "/product/{id}");
+        assert_eq!(
+            normalizer.normalize("/order/3f2504e0-4f89-11d3-9a0c-0305e82c3301/items"),
+            "/order/{uuid}/items"
+        );
+        assert_eq!(
+            normalizer.normalize("/static/d41d8cd98f00b204e9800998ecf8427e.js"),
+            "/static/d41d8cd98f00b204e9800998ecf8427e.js"
+        );
+        assert_eq!(
+            normalizer.normalize("/blob/d41d8cd98f00b204e9800998ecf8427e"),
+            "/blob/{hash}"
+        );
+    }
+
+    #[test]
+    fn keeps_words_and_short_hex() {
+        let normalizer = normalizer(false, true, &[]);
+        assert_eq!(normalizer.normalize("/v2/cafe/beef"), "/v2/cafe/beef");
+        assert_eq!(normalizer.normalize("/"), "/");
+        assert_eq!(normalizer.normalize("/a//1"), "/a//{id}");
+        assert_eq!(
+            normalizer.normalize("/3f2504e0-4f89-11d3-9a0c"),
+            "/3f2504e0-4f89-11d3-9a0c"
+        );
+    }
+
+    #[test]
+    fn keeps_method_and_protocol_of_request_lines() {
+        let normalizer = normalizer(false, true, &[]);
+        assert_eq!(
+            normalizer.normalize("GET /product/123?page=2 HTTP/1.1"),
+            "GET /product/{id}?page=2 HTTP/1.1"
+        );
+        assert_eq!(
+            normalizer.normalize("GET /product/123"),
+            "GET /product/{id}"
+        );
+    }
+
+    #[test]
+    fn strips_the_query() {
+        let normalizer = normalizer(true, false, &[]);
+        assert_eq!(
+            normalizer.normalize("GET /search/42?q=shoes HTTP/1.1"),
+            "GET /search/42 HTTP/1.1"
+        );
+        assert_eq!(normalizer.normalize("/search?"), "/search");
+        assert!(normalizer.is_active());
+        assert!(!UrlNormalizer::new(false, false, &[]).unwrap().is_active());
+    }
+
+    #[test]
+    fn applies_rewrite_rules_in_order_after_collapsing() {
+        let normalizer = normalizer(
+            false,
+            true,
+            &[
+                r"^/user/[^/]+/profile=>/user/{name}/profile",
+                r"^/(?P<lang>en|de)/=>/${lang}-site/",
+            ],
+        );
+        assert_eq!(
+            normalizer.normalize("/user/alice/profile?tab=1"),
+            "/user/{name}/profile?tab=1"
+        );
+        assert_eq!(normalizer.normalize("/de/item/7"), "/de-site/item/{id}");
+    }
+
+    #[test]
+    fn replacement_may_contain_the_separator() {
+        let normalizer = normalizer(false, false, &["^/old=>/new=>x"]);
+        assert_eq!(normalizer.normalize("/old/page"), "/new=>x/page");
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let error = UrlNormalizer::new(false, false, &["/product/\\d+".to_string()]).unwrap_err();
+        assert!(error.to_string().contains("expected PATTERN=>REPLACEMENT"));
+        assert!(UrlNormalizer::new(false, false, &["/product/(=>x".to_string()]).is_err());
+    }
+}